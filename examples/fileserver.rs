@@ -1,34 +1,26 @@
 extern crate lhi;
 
 use kern::Fail;
-use lhi::server::{listen, load_certificate, respond, unsecure::listen_redirect, HttpSettings};
-use std::fs::File;
-use std::io::prelude::Read;
-use std::sync::{Arc, RwLock};
+use lhi::server::{
+    fs::FileServer, listen, load_certificate, unsecure::listen_redirect, Chain, HttpRequest,
+    HttpSettings, Next,
+};
+use std::sync::atomic::{AtomicU32, Ordering};
 
 fn main() {
     let config = load_certificate("examples/cert.pem", "examples/key.pem").unwrap();
     let http_settings = HttpSettings::new();
+    let num = AtomicU32::new(0);
+    let file_server = FileServer::new(".").unwrap().set_listing(true);
     let listeners = listen(
         "[::]:8480",
         4,
         http_settings,
         config,
-        |req, shared| {
-            let mut num = shared.write().unwrap();
-            *num += 1;
-            dbg!(*num);
-            let req = req?;
-            let filename = req
-                .get()
-                .get("file")
-                .ok_or_else(|| Fail::new("filename missing, try adding ?file=... to the url"))?;
-            let mut file = File::open(filename).or_else(Fail::from)?;
-            let mut buf = String::new();
-            file.read_to_string(&mut buf).or_else(Fail::from)?;
-            Ok(respond(buf, "text/html", None))
-        },
-        Arc::new(RwLock::new(0u32)),
+        Chain::new(file_server).with(move |req: Result<HttpRequest, Fail>, next: Next| {
+            dbg!(num.fetch_add(1, Ordering::SeqCst) + 1);
+            next(req)
+        }),
     )
     .unwrap();
     listen_redirect("[::]:8080", "localhost:8480".to_string()).unwrap();
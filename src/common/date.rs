@@ -0,0 +1,112 @@
+//! HTTP dates
+
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Day names starting with Monday
+const DAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+
+/// Month names
+const MONTHS: [&str; 12] = [
+    "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+];
+
+/// Format time as HTTP date (e.g. "Sun, 06 Nov 1994 08:49:37 GMT")
+pub fn http_date(time: SystemTime) -> String {
+    // seconds since epoch (times before epoch are clamped)
+    let secs = time
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default();
+    let (days, secs) = (secs / 86400, secs % 86400);
+    let (year, month, day) = civil_from_days(days as i64);
+
+    // 1970-01-01 was a Thursday
+    format!(
+        "{}, {:02} {} {} {:02}:{:02}:{:02} GMT",
+        DAYS[((days + 3) % 7) as usize],
+        day,
+        MONTHS[(month - 1) as usize],
+        year,
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Parse HTTP date (IMF-fixdate, RFC 850 or asctime format)
+pub fn parse_http_date(date: &str) -> Option<SystemTime> {
+    // split into parts without day name
+    let date = date.trim();
+    let date = date.split_once(',').map_or(date, |(_, date)| date);
+    let parts: Vec<&str> = date.split_whitespace().collect();
+
+    // get day, month, year and time by format
+    let (day, month, year, time) = match parts.as_slice() {
+        // IMF-fixdate: 06 Nov 1994 08:49:37 GMT
+        [day, month, year, time, "GMT"] => (*day, *month, parse_year(year)?, *time),
+        // RFC 850: 06-Nov-94 08:49:37 GMT
+        [date, time, "GMT"] => {
+            let mut ds = date.split('-');
+            let (day, month, year) = (ds.next()?, ds.next()?, ds.next()?);
+            (day, month, parse_year(year)?, *time)
+        }
+        // asctime: Sun Nov  6 08:49:37 1994
+        [_, month, day, time, year] => (*day, *month, parse_year(year)?, *time),
+        _ => return None,
+    };
+    let day: u64 = day.parse().ok()?;
+    let month = MONTHS.iter().position(|name| *name == month)? as u64 + 1;
+    let mut ts = time.split(':');
+    let (hour, minute, second): (u64, u64, u64) = (
+        ts.next()?.parse().ok()?,
+        ts.next()?.parse().ok()?,
+        ts.next()?.parse().ok()?,
+    );
+    if ts.next().is_some() || day == 0 || day > 31 || hour > 23 || minute > 59 || second > 60 {
+        return None;
+    }
+
+    // create time
+    let days = days_from_civil(year, month, day);
+    if days < 0 {
+        return None;
+    }
+    let secs = days as u64 * 86400 + hour * 3600 + minute * 60 + second;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+/// Parse four or two digit year (two digit years are 1970-2069)
+fn parse_year(year: &str) -> Option<i64> {
+    let value: i64 = year.parse().ok()?;
+    match year.len() {
+        4 => Some(value),
+        2 if value < 70 => Some(2000 + value),
+        2 => Some(1900 + value),
+        _ => None,
+    }
+}
+
+/// Convert days since epoch to year, month and day
+fn civil_from_days(days: i64) -> (i64, u64, u64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u64;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u64;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+/// Convert year, month and day to days since epoch
+fn days_from_civil(year: i64, month: u64, day: u64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let yoe = year.rem_euclid(400);
+    let mp = if month > 2 { month - 3 } else { month + 9 } as i64;
+    let doy = (153 * mp + 2) / 5 + day as i64 - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
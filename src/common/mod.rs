@@ -1,5 +1,9 @@
 //! Commons
 
 mod consts;
+mod date;
+mod url;
 
 pub use consts::*;
+pub use date::*;
+pub use url::*;
@@ -0,0 +1,64 @@
+//! URL encoding
+
+use kern::Fail;
+
+/// Decode percent-encoded string to bytes (optionally '+' as space)
+pub fn percent_decode(raw: &str, plus_as_space: bool) -> Result<Vec<u8>, Fail> {
+    // iterate through bytes
+    let raw = raw.as_bytes();
+    let mut decoded = Vec::with_capacity(raw.len());
+    let mut i = 0;
+    while i < raw.len() {
+        match raw[i] {
+            b'%' => {
+                // parse two hex digits
+                let hex = raw
+                    .get(i + 1..i + 3)
+                    .ok_or_else(|| Fail::new("Incomplete percent-encoding"))?;
+                let (high, low) = (hex_value(hex[0]), hex_value(hex[1]));
+                match (high, low) {
+                    (Some(high), Some(low)) => decoded.push(high << 4 | low),
+                    _ => return Fail::from("Invalid percent-encoding"),
+                }
+                i += 3;
+            }
+            b'+' if plus_as_space => {
+                decoded.push(b' ');
+                i += 1;
+            }
+            b => {
+                decoded.push(b);
+                i += 1;
+            }
+        }
+    }
+
+    // return decoded bytes
+    Ok(decoded)
+}
+
+/// Decode percent-encoded string to UTF-8 string (optionally '+' as space)
+pub fn percent_decode_utf8(raw: &str, plus_as_space: bool) -> Result<String, Fail> {
+    String::from_utf8(percent_decode(raw, plus_as_space)?).or_else(Fail::from)
+}
+
+/// Percent-encode string (unreserved characters and '/' are kept)
+pub fn percent_encode(raw: impl AsRef<[u8]>) -> String {
+    // iterate through bytes
+    let mut encoded = String::new();
+    for &b in raw.as_ref() {
+        if b.is_ascii_alphanumeric() || b"-._~/".contains(&b) {
+            encoded.push(b as char);
+        } else {
+            encoded.push_str(&format!("%{:02X}", b));
+        }
+    }
+
+    // return encoded string
+    encoded
+}
+
+/// Get value of hex digit
+fn hex_value(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|v| v as u8)
+}
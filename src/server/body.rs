@@ -0,0 +1,333 @@
+//! HTTP request body
+
+use crate::server::{ChunkedDecoder, ContentCoding, HeaderMap};
+use flate2::write::{GzDecoder, ZlibDecoder};
+use kern::Fail;
+use std::fmt::{Debug, Formatter, Result as FmtResult};
+use std::io::{self, prelude::Read, prelude::Write};
+
+/// Maximum compressed input per decoder write (limits decompression per step)
+const DECODE_STEP: usize = 1024;
+
+/// Error message if body exceeds maximum size (sent as 413)
+pub(crate) const BODY_TOO_LARGE: &str = "Max body size exceeded";
+
+/// Request body framing
+#[derive(Clone, Debug)]
+enum Framing {
+    Empty,
+    Length(usize),
+    Chunked(ChunkedDecoder),
+}
+
+/// Request body state (framing and buffered input of a connection)
+#[derive(Clone, Debug)]
+pub struct BodyState {
+    input: Vec<u8>,
+    decoded: Vec<u8>,
+    framing: Framing,
+    buffer_size: usize,
+}
+
+impl BodyState {
+    /// Create new body state with already read input
+    pub fn new(input: Vec<u8>, buffer_size: usize) -> Self {
+        Self {
+            input,
+            decoded: Vec::new(),
+            framing: Framing::Empty,
+            buffer_size,
+        }
+    }
+
+    /// Set body length from Content-Length
+    pub fn set_length(&mut self, length: usize) {
+        self.framing = Framing::Length(length);
+    }
+
+    /// Set chunked body with maximum trailer size
+    pub fn set_chunked(&mut self, max_trailer_size: usize) {
+        self.framing =
+            Framing::Chunked(ChunkedDecoder::new().set_max_trailer_size(max_trailer_size));
+    }
+
+    /// Remaining body length if known
+    pub fn remaining(&self) -> Option<usize> {
+        match &self.framing {
+            Framing::Empty => Some(0),
+            Framing::Length(remaining) => Some(*remaining),
+            Framing::Chunked(decoder) if decoder.is_done() && self.decoded.is_empty() => Some(0),
+            Framing::Chunked(_) => None,
+        }
+    }
+
+    /// Check if body has been read completely
+    pub fn is_done(&self) -> bool {
+        self.remaining() == Some(0)
+    }
+
+    /// Take unread input after the body (e.g. pipelined requests)
+    pub fn take_input(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.input)
+    }
+
+    /// Get trailers of chunked body (available after reading body)
+    pub fn trailers(&self) -> Option<&HeaderMap> {
+        match &self.framing {
+            Framing::Chunked(decoder) => Some(decoder.trailers()),
+            _ => None,
+        }
+    }
+
+    /// Read next buffer from stream into input
+    fn fill_input(&mut self, stream: &mut dyn Read) -> io::Result<()> {
+        // read next buffer
+        let mut buf = vec![0u8; self.buffer_size];
+        let length = stream.read(&mut buf)?;
+        if length == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::UnexpectedEof,
+                "Stream closed before end of body",
+            ));
+        }
+        self.input.extend_from_slice(&buf[..length]);
+        Ok(())
+    }
+}
+
+/// Content decoder writing into buffer
+enum Decoder {
+    Gzip(GzDecoder<Vec<u8>>),
+    Deflate(ZlibDecoder<Vec<u8>>),
+}
+
+/// Request body content decoder with decoded size limit
+struct ContentDecoder {
+    decoder: Decoder,
+    position: usize,
+    total: usize,
+    limit: usize,
+    done: bool,
+}
+
+impl ContentDecoder {
+    /// Create decoder for coding
+    fn new(coding: ContentCoding, limit: usize) -> Self {
+        Self {
+            decoder: match coding {
+                ContentCoding::Gzip => Decoder::Gzip(GzDecoder::new(Vec::new())),
+                ContentCoding::Deflate => Decoder::Deflate(ZlibDecoder::new(Vec::new())),
+            },
+            position: 0,
+            total: 0,
+            limit,
+            done: false,
+        }
+    }
+
+    /// Get decoded buffer
+    fn output(&mut self) -> &mut Vec<u8> {
+        match &mut self.decoder {
+            Decoder::Gzip(decoder) => decoder.get_mut(),
+            Decoder::Deflate(decoder) => decoder.get_mut(),
+        }
+    }
+
+    /// Decode input (end of input if empty) and check decoded size
+    fn decode(&mut self, input: &[u8]) -> io::Result<()> {
+        // decode in steps
+        for step in input.chunks(DECODE_STEP) {
+            let length = self.output().len();
+            match &mut self.decoder {
+                Decoder::Gzip(decoder) => decoder.write_all(step)?,
+                Decoder::Deflate(decoder) => decoder.write_all(step)?,
+            }
+            self.check_limit(length)?;
+        }
+
+        // finish at end of input
+        if input.is_empty() {
+            let length = self.output().len();
+            match &mut self.decoder {
+                Decoder::Gzip(decoder) => decoder.try_finish()?,
+                Decoder::Deflate(decoder) => decoder.try_finish()?,
+            }
+            self.check_limit(length)?;
+            self.done = true;
+        }
+        Ok(())
+    }
+
+    /// Add newly decoded length to total and check limit
+    fn check_limit(&mut self, previous: usize) -> io::Result<()> {
+        self.total += self.output().len() - previous;
+        match self.total > self.limit {
+            true => Err(io::Error::new(io::ErrorKind::InvalidData, BODY_TOO_LARGE)),
+            false => Ok(()),
+        }
+    }
+
+    /// Copy decoded data into buffer
+    fn read(&mut self, buf: &mut [u8]) -> usize {
+        let position = self.position;
+        let output = self.output();
+        let length = buf.len().min(output.len() - position);
+        buf[..length].copy_from_slice(&output[position..(position + length)]);
+
+        // clear buffer if read completely
+        if position + length == output.len() {
+            output.clear();
+            self.position = 0;
+        } else {
+            self.position += length;
+        }
+        length
+    }
+}
+
+/// Streaming request body reader (decodes Content-Encoding if set)
+pub struct BodyReader<'a> {
+    stream: &'a mut (dyn Read + 'a),
+    state: &'a mut BodyState,
+    decoder: Option<ContentDecoder>,
+}
+
+impl<'a> BodyReader<'a> {
+    /// Create body reader over stream
+    pub fn new(stream: &'a mut (dyn Read + 'a), state: &'a mut BodyState) -> Self {
+        Self {
+            stream,
+            state,
+            decoder: None,
+        }
+    }
+
+    /// Decode content coding with decoded size limit (ignored for empty bodies)
+    pub fn set_content_coding(&mut self, coding: ContentCoding, limit: usize) {
+        if !self.state.is_done() {
+            self.decoder = Some(ContentDecoder::new(coding, limit));
+        }
+    }
+
+    /// Get body state
+    pub fn state(&self) -> &BodyState {
+        self.state
+    }
+
+    /// Read remaining body into buffer with size limit
+    pub fn read_to_limit(&mut self, buf: &mut Vec<u8>, limit: usize) -> Result<(), Fail> {
+        // check if known length is ok
+        if let Some(remaining) = self.state.remaining() {
+            if buf.len() + remaining > limit {
+                return Fail::from(BODY_TOO_LARGE);
+            }
+        }
+
+        // read until end of body
+        let mut temp = vec![0u8; self.state.buffer_size];
+        loop {
+            let length = self.read(&mut temp).or_else(Fail::from)?;
+            if length == 0 {
+                break;
+            } else if buf.len() + length > limit {
+                return Fail::from(BODY_TOO_LARGE);
+            }
+            buf.extend_from_slice(&temp[..length]);
+        }
+        Ok(())
+    }
+}
+
+impl Read for BodyReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        // read framed body directly
+        let decoder = match &mut self.decoder {
+            Some(decoder) => decoder,
+            None => return read_framed(self.stream, self.state, buf),
+        };
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // decode framed body
+        let mut temp = vec![0u8; self.state.buffer_size];
+        loop {
+            let length = decoder.read(buf);
+            if length > 0 || decoder.done {
+                return Ok(length);
+            }
+            let length = read_framed(self.stream, self.state, &mut temp)?;
+            decoder.decode(&temp[..length])?;
+        }
+    }
+}
+
+/// Read body with framing
+fn read_framed(stream: &mut dyn Read, state: &mut BodyState, buf: &mut [u8]) -> io::Result<usize> {
+    loop {
+        match &mut state.framing {
+            Framing::Empty => return Ok(0),
+            Framing::Length(remaining) => {
+                // end of body
+                let max = buf.len().min(*remaining);
+                if max == 0 {
+                    return Ok(0);
+                }
+
+                // read from buffered input or stream
+                let length = if !state.input.is_empty() {
+                    let length = max.min(state.input.len());
+                    buf[..length].copy_from_slice(&state.input[..length]);
+                    state.input.drain(..length);
+                    length
+                } else {
+                    let length = stream.read(&mut buf[..max])?;
+                    if length == 0 {
+                        return Err(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "Stream closed before end of body",
+                        ));
+                    }
+                    length
+                };
+                *remaining -= length;
+                return Ok(length);
+            }
+            Framing::Chunked(decoder) => {
+                // return decoded data
+                if !state.decoded.is_empty() {
+                    let length = buf.len().min(state.decoded.len());
+                    buf[..length].copy_from_slice(&state.decoded[..length]);
+                    state.decoded.drain(..length);
+                    return Ok(length);
+                } else if decoder.is_done() {
+                    return Ok(0);
+                }
+
+                // decode buffered input
+                if !state.input.is_empty() {
+                    let consumed = decoder
+                        .decode(&state.input, &mut state.decoded)
+                        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                    state.input.drain(..consumed);
+                    if !state.decoded.is_empty() || decoder.is_done() {
+                        continue;
+                    }
+                }
+
+                // read more
+                state.fill_input(stream)?;
+            }
+        }
+    }
+}
+
+impl Debug for BodyReader<'_> {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        formatter
+            .debug_struct("BodyReader")
+            .field("state", &self.state)
+            .field("decoding", &self.decoder.is_some())
+            .finish()
+    }
+}
@@ -0,0 +1,224 @@
+//! Chunked transfer-encoding
+
+use crate::server::{split_header_line, HeaderMap};
+use kern::Fail;
+use std::io::prelude::Write;
+use std::io::Result as IoResult;
+
+/// Maximum length of chunk size and trailer lines
+const MAX_LINE_LENGTH: usize = 4096;
+
+/// Default maximum size of all trailer lines
+const MAX_TRAILER_SIZE: usize = 8192;
+
+/// Chunked decoder state
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ChunkState {
+    Size,
+    Data(usize),
+    DataEnd,
+    Trailer,
+    Done,
+}
+
+/// Incremental chunked transfer-encoding decoder
+#[derive(Clone, Debug)]
+pub struct ChunkedDecoder {
+    state: ChunkState,
+    line: Vec<u8>,
+    trailers: HeaderMap,
+    trailer_size: usize,
+    max_trailer_size: usize,
+}
+
+impl Default for ChunkedDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ChunkedDecoder {
+    /// Create new decoder
+    pub fn new() -> Self {
+        Self {
+            state: ChunkState::Size,
+            line: Vec::new(),
+            trailers: HeaderMap::new(),
+            trailer_size: 0,
+            max_trailer_size: MAX_TRAILER_SIZE,
+        }
+    }
+
+    /// Set maximum size of all trailer lines (including line endings)
+    pub fn set_max_trailer_size(mut self, max_trailer_size: usize) -> Self {
+        self.max_trailer_size = max_trailer_size;
+        self
+    }
+
+    /// Check if last chunk and trailers have been decoded
+    pub fn is_done(&self) -> bool {
+        self.state == ChunkState::Done
+    }
+
+    /// Get trailers (lowercase names)
+    pub fn trailers(&self) -> &HeaderMap {
+        &self.trailers
+    }
+
+    /// Take trailers
+    pub fn take_trailers(&mut self) -> HeaderMap {
+        std::mem::take(&mut self.trailers)
+    }
+
+    /// Decode input and append data to output, returns number of consumed input bytes
+    pub fn decode(&mut self, input: &[u8], output: &mut Vec<u8>) -> Result<usize, Fail> {
+        // iterate until input consumed or done
+        let mut pos = 0;
+        while pos < input.len() {
+            match self.state {
+                ChunkState::Size => {
+                    // read chunk size line
+                    pos += self.read_line(&input[pos..])?;
+                    if let Some(line) = self.take_line() {
+                        // ignore chunk extensions, hex digits only (no sign)
+                        let size = line.split(';').next().unwrap_or_default().trim();
+                        let size = Some(size)
+                            .filter(|size| {
+                                !size.is_empty() && size.bytes().all(|b| b.is_ascii_hexdigit())
+                            })
+                            .and_then(|size| usize::from_str_radix(size, 16).ok())
+                            .ok_or_else(|| Fail::new("Invalid chunk size"))?;
+                        self.state = if size == 0 {
+                            ChunkState::Trailer
+                        } else {
+                            ChunkState::Data(size)
+                        };
+                    }
+                }
+                ChunkState::Data(remaining) => {
+                    // copy chunk data
+                    let length = remaining.min(input.len() - pos);
+                    output.extend_from_slice(&input[pos..(pos + length)]);
+                    pos += length;
+                    self.state = if remaining == length {
+                        ChunkState::DataEnd
+                    } else {
+                        ChunkState::Data(remaining - length)
+                    };
+                }
+                ChunkState::DataEnd => {
+                    // expect empty line after chunk data
+                    pos += self.read_line(&input[pos..])?;
+                    if let Some(line) = self.take_line() {
+                        if !line.is_empty() {
+                            return Fail::from("Missing CRLF after chunk data");
+                        }
+                        self.state = ChunkState::Size;
+                    }
+                }
+                ChunkState::Trailer => {
+                    // read trailer lines until empty line and check max size
+                    let length = self.read_line(&input[pos..])?;
+                    pos += length;
+                    self.trailer_size += length;
+                    if self.trailer_size > self.max_trailer_size {
+                        return Fail::from("Max trailer size exceeded");
+                    }
+                    if let Some(line) = self.take_line() {
+                        if line.is_empty() {
+                            self.state = ChunkState::Done;
+                        } else {
+                            let (key, value) = split_header_line(&line)
+                                .ok_or_else(|| Fail::new("Malformed trailer line"))?;
+                            self.trailers
+                                .append(key, value)
+                                .or_else(|_| Fail::from("Malformed trailer line"))?;
+                        }
+                    }
+                }
+                ChunkState::Done => break,
+            }
+        }
+
+        // return consumed length
+        Ok(pos)
+    }
+
+    /// Read bytes until \n into line buffer, returns number of consumed bytes
+    fn read_line(&mut self, input: &[u8]) -> Result<usize, Fail> {
+        // find line end
+        let length = match input.iter().position(|&b| b == b'\n') {
+            Some(end) => end + 1,
+            None => input.len(),
+        };
+
+        // add to line buffer and check max length
+        self.line.extend_from_slice(&input[..length]);
+        if self.line.len() > MAX_LINE_LENGTH {
+            return Fail::from("Chunk line too long");
+        }
+        Ok(length)
+    }
+
+    /// Take complete line from line buffer without line ending
+    fn take_line(&mut self) -> Option<String> {
+        // check if line complete
+        if self.line.last() != Some(&b'\n') {
+            return None;
+        }
+
+        // remove line ending and return
+        let mut line = std::mem::take(&mut self.line);
+        line.pop();
+        if line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        Some(String::from_utf8_lossy(&line).to_string())
+    }
+}
+
+/// Chunked transfer-encoding writer (every write is sent as one chunk)
+#[derive(Debug)]
+pub struct ChunkedWriter<W: Write> {
+    writer: W,
+}
+
+impl<W: Write> ChunkedWriter<W> {
+    /// Create new writer
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    /// Write last chunk and trailers, returns inner writer
+    pub fn finish(mut self, trailers: &HeaderMap) -> IoResult<W> {
+        // last chunk
+        self.writer.write_all(b"0\r\n")?;
+
+        // trailers and end
+        for (name, value) in trailers.iter() {
+            write!(self.writer, "{}: {}\r\n", name, value)?;
+        }
+        self.writer.write_all(b"\r\n")?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+}
+
+impl<W: Write> Write for ChunkedWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        // empty chunk would end body
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // write chunk
+        write!(self.writer, "{:x}\r\n", buf.len())?;
+        self.writer.write_all(buf)?;
+        self.writer.write_all(b"\r\n")?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        self.writer.flush()
+    }
+}
@@ -0,0 +1,328 @@
+//! Response compression
+
+use crate::server::{
+    BodyWriter, HttpRequest, Middleware, Next, Response, ResponseBody, StatusCode, WriteBody,
+};
+use flate2::write::{GzEncoder, ZlibEncoder};
+use flate2::Compression as Level;
+use kern::Fail;
+use std::io::prelude::{Read, Write};
+use std::io::{copy, Result as IoResult};
+
+/// Content coding
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ContentCoding {
+    Gzip,
+    Deflate,
+}
+
+impl ContentCoding {
+    /// Get content coding name
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Gzip => "gzip",
+            Self::Deflate => "deflate",
+        }
+    }
+
+    /// Parse content coding name (x-gzip is gzip)
+    pub fn parse(coding: &str) -> Option<Self> {
+        match coding.trim().to_lowercase().as_str() {
+            "gzip" | "x-gzip" => Some(Self::Gzip),
+            "deflate" => Some(Self::Deflate),
+            _ => None,
+        }
+    }
+}
+
+/// Choose preferred supported coding of Accept-Encoding header (gzip on equal q-values)
+pub fn negotiate_encoding(accept_encoding: &str) -> Option<ContentCoding> {
+    // parse codings with q-values
+    let mut gzip = None;
+    let mut deflate = None;
+    let mut any = None;
+    for entry in accept_encoding.split(',') {
+        let mut params = entry.split(';');
+        let coding = params.next().unwrap_or_default().trim().to_lowercase();
+        let q = params
+            .filter_map(|param| param.split_once('='))
+            .find(|(key, _)| key.trim().eq_ignore_ascii_case("q"))
+            .map_or(Some(1.0), |(_, q)| q.trim().parse::<f32>().ok())
+            .filter(|q| (0.0..=1.0).contains(q))
+            .unwrap_or(0.0);
+        match coding.as_str() {
+            "*" => any = Some(q),
+            coding => match ContentCoding::parse(coding) {
+                Some(ContentCoding::Gzip) => gzip = Some(q),
+                Some(ContentCoding::Deflate) => deflate = Some(q),
+                None => {}
+            },
+        }
+    }
+
+    // unlisted codings use wildcard q-value
+    let gzip = gzip.or(any).unwrap_or(0.0);
+    let deflate = deflate.or(any).unwrap_or(0.0);
+    match gzip >= deflate {
+        true if gzip > 0.0 => Some(ContentCoding::Gzip),
+        false if deflate > 0.0 => Some(ContentCoding::Deflate),
+        _ => None,
+    }
+}
+
+/// Compressing writer
+enum Encoder<W: Write> {
+    Gzip(GzEncoder<W>),
+    Deflate(ZlibEncoder<W>),
+}
+
+impl<W: Write> Encoder<W> {
+    /// Create encoder for coding
+    fn new(coding: ContentCoding, writer: W, level: u32) -> Self {
+        match coding {
+            ContentCoding::Gzip => Self::Gzip(GzEncoder::new(writer, Level::new(level))),
+            ContentCoding::Deflate => Self::Deflate(ZlibEncoder::new(writer, Level::new(level))),
+        }
+    }
+
+    /// Write remaining data, returns inner writer
+    fn finish(self) -> IoResult<W> {
+        match self {
+            Self::Gzip(encoder) => encoder.finish(),
+            Self::Deflate(encoder) => encoder.finish(),
+        }
+    }
+}
+
+impl<W: Write> Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match self {
+            Self::Gzip(encoder) => encoder.write(buf),
+            Self::Deflate(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match self {
+            Self::Gzip(encoder) => encoder.flush(),
+            Self::Deflate(encoder) => encoder.flush(),
+        }
+    }
+}
+
+/// Response compression (middleware or used directly)
+///
+/// Seekable bodies (e.g. files) of range requests are not compressed to keep range support
+#[derive(Clone, Debug)]
+pub struct Compression {
+    min_size: u64,
+    content_types: Vec<String>,
+    level: u32,
+}
+
+impl Default for Compression {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compression {
+    /// Create with default values (text types, JSON, JavaScript, XML and SVG from 1 KiB)
+    pub fn new() -> Self {
+        Self {
+            min_size: 1024,
+            content_types: [
+                "text/",
+                "application/json",
+                "application/javascript",
+                "application/xml",
+                "image/svg+xml",
+            ]
+            .iter()
+            .map(|content_type| content_type.to_string())
+            .collect(),
+            level: 6,
+        }
+    }
+
+    /// Set minimum body size (streaming bodies of unknown length are always compressed)
+    pub fn set_min_size(mut self, min_size: u64) -> Self {
+        self.min_size = min_size;
+        self
+    }
+
+    /// Set compressible content types (entries ending with '/' match all subtypes)
+    pub fn set_content_types(mut self, content_types: &[&str]) -> Self {
+        self.content_types = content_types
+            .iter()
+            .map(|content_type| content_type.to_lowercase())
+            .collect();
+        self
+    }
+
+    /// Set compression level (0-9)
+    pub fn set_level(mut self, level: u32) -> Self {
+        self.level = level.min(9);
+        self
+    }
+
+    /// Check if content type is compressible
+    fn is_compressible(&self, content_type: &str) -> bool {
+        // match media type without parameters
+        let media_type = content_type
+            .split(';')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase();
+        self.content_types
+            .iter()
+            .any(|allowed| match allowed.ends_with('/') {
+                true => media_type.starts_with(allowed.as_str()),
+                false => media_type == *allowed,
+            })
+    }
+
+    /// Compress response body negotiated by Accept-Encoding (adds Vary: Accept-Encoding)
+    ///
+    /// Seekable bodies are compressed into streaming bodies (use compress_range for range requests)
+    pub fn compress(&self, response: Response, accept_encoding: Option<&str>) -> Response {
+        self.compress_range(response, accept_encoding, None)
+    }
+
+    /// Compress response body unless the request has a range header and the body is seekable
+    pub fn compress_range(
+        &self,
+        mut response: Response,
+        accept_encoding: Option<&str>,
+        range: Option<&str>,
+    ) -> Response {
+        // only successful, complete and compressible responses (seekable bodies without range)
+        let status = response.status();
+        if !status.is_success()
+            || status == StatusCode::NO_CONTENT
+            || status == StatusCode::PARTIAL_CONTENT
+            || (range.is_some() && matches!(response.body(), ResponseBody::Seekable(_, _)))
+            || response.header("content-encoding").is_some()
+            || response
+                .header("cache-control")
+                .is_some_and(|value| value.to_lowercase().contains("no-transform"))
+            || !response
+                .header("content-type")
+                .is_some_and(|content_type| self.is_compressible(content_type))
+        {
+            return response;
+        }
+
+        // response depends on Accept-Encoding
+        response = match response.header("vary") {
+            Some(vary)
+                if vary.split(',').any(|value| {
+                    let value = value.trim();
+                    value == "*" || value.eq_ignore_ascii_case("accept-encoding")
+                }) =>
+            {
+                response
+            }
+            Some(vary) => {
+                let vary = format!("{}, Accept-Encoding", vary);
+                response.set_header("vary", vary)
+            }
+            None => response.set_header("vary", "Accept-Encoding"),
+        };
+
+        // negotiate coding and check size
+        let coding = match accept_encoding.and_then(negotiate_encoding) {
+            Some(coding) => coding,
+            None => return response,
+        };
+        if response
+            .body()
+            .len()
+            .is_some_and(|length| length < self.min_size)
+        {
+            return response;
+        }
+
+        // compressed representation has different ETag
+        if let Some(etag) = response.header("etag") {
+            if let Some(etag) = etag.strip_suffix('"') {
+                let etag = format!("{}-{}\"", etag, coding.as_str());
+                response = response.set_header("etag", etag);
+            }
+        }
+
+        // compress body
+        let level = self.level;
+        let body = match response.take_body() {
+            ResponseBody::Bytes(bytes) => {
+                let mut encoder = Encoder::new(coding, Vec::new(), level);
+                match encoder.write_all(&bytes).and_then(|_| encoder.finish()) {
+                    Ok(compressed) => ResponseBody::Bytes(compressed),
+                    Err(_) => return response.set_body(bytes),
+                }
+            }
+            ResponseBody::Reader(reader, _) => {
+                ResponseBody::Writer(encode_reader(reader, coding, level))
+            }
+            ResponseBody::Seekable(reader, _) => {
+                ResponseBody::Writer(encode_reader(reader, coding, level))
+            }
+            ResponseBody::Writer(write_body) => {
+                ResponseBody::Writer(encode_writer(write_body, coding, level))
+            }
+        };
+        response
+            .set_header("content-encoding", coding.as_str())
+            .set_response_body(body)
+    }
+}
+
+/// Create body writer compressing reader
+fn encode_reader(
+    mut reader: impl Read + Send + 'static,
+    coding: ContentCoding,
+    level: u32,
+) -> WriteBody {
+    Box::new(move |writer: &mut BodyWriter| {
+        let mut encoder = Encoder::new(coding, writer, level);
+        copy(&mut reader, &mut encoder).or_else(Fail::from)?;
+        encoder.finish().or_else(Fail::from)?;
+        Ok(())
+    })
+}
+
+/// Create body writer compressing body writer (trailers are kept)
+fn encode_writer(write_body: WriteBody, coding: ContentCoding, level: u32) -> WriteBody {
+    Box::new(move |writer: &mut BodyWriter| {
+        // write body to encoder
+        let mut encoder = Encoder::new(coding, writer, level);
+        let mut inner = BodyWriter::new(&mut encoder, false);
+        write_body(&mut inner)?;
+        let trailers = inner.take_trailers();
+        inner.finish().or_else(Fail::from)?;
+
+        // finish and forward trailers
+        let writer = encoder.finish().or_else(Fail::from)?;
+        for (name, value) in trailers {
+            writer.add_trailer(name, value);
+        }
+        Ok(())
+    })
+}
+
+impl Middleware for Compression {
+    fn handle(&self, req: Result<HttpRequest, Fail>, next: Next) -> Result<Response, Fail> {
+        // keep negotiation and range headers
+        let header = |name: &str| {
+            req.as_ref()
+                .ok()
+                .and_then(|req| req.headers().get(name).map(|value| value.to_string()))
+        };
+        let (accept_encoding, range) = (header("accept-encoding"), header("range"));
+
+        // compress response of next layer
+        let response = next(req)?;
+        Ok(self.compress_range(response, accept_encoding.as_deref(), range.as_deref()))
+    }
+}
@@ -0,0 +1,196 @@
+//! Conditional requests
+
+use crate::common::parse_http_date;
+use crate::server::{error_response, HttpMethod, HttpRequest, Response, StatusCode};
+use std::fs::Metadata;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Headers kept in 304 responses
+const NOT_MODIFIED_HEADERS: [&str; 7] = [
+    "cache-control",
+    "content-location",
+    "date",
+    "etag",
+    "expires",
+    "last-modified",
+    "vary",
+];
+
+/// Create strong ETag from content hash
+pub fn strong_etag(content: &[u8]) -> String {
+    format!("\"{:016x}\"", fnv1a(content))
+}
+
+/// Create weak ETag from content hash
+pub fn weak_etag(content: &[u8]) -> String {
+    format!("W/{}", strong_etag(content))
+}
+
+/// Create ETag from file length and modification time
+pub fn file_etag(metadata: &Metadata) -> String {
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .unwrap_or_default();
+    format!(
+        "\"{:x}-{:x}{:08x}\"",
+        metadata.len(),
+        modified.as_secs(),
+        modified.subsec_nanos()
+    )
+}
+
+/// 64-bit FNV-1a hash
+fn fnv1a(content: &[u8]) -> u64 {
+    content.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Compare ETags (weak comparison ignores W/ prefix)
+fn etag_matches(a: &str, b: &str, weak: bool) -> bool {
+    match (a.strip_prefix("W/"), b.strip_prefix("W/")) {
+        (None, None) => a == b,
+        (a_weak, b_weak) if weak => a_weak.unwrap_or(a) == b_weak.unwrap_or(b),
+        _ => false,
+    }
+}
+
+/// Check if ETag list header (or "*") contains ETag
+fn etag_list_matches(list: &str, etag: Option<&str>, weak: bool) -> bool {
+    // any current representation
+    let etag = match etag {
+        _ if list.trim() == "*" => return true,
+        Some(etag) => etag,
+        None => return false,
+    };
+
+    // iterate through quoted entity tags (may contain commas)
+    let mut rest = list;
+    loop {
+        rest = rest.trim_start_matches(|c: char| c == ',' || c.is_whitespace());
+        let (prefix, tag) = match rest.strip_prefix("W/") {
+            Some(tag) => ("W/", tag),
+            None => ("", rest),
+        };
+        let end = match tag.strip_prefix('"').and_then(|tag| tag.find('"')) {
+            Some(end) => prefix.len() + end + 2,
+            None => return false,
+        };
+        if etag_matches(&rest[..end], etag, weak) {
+            return true;
+        }
+        rest = &rest[end..];
+    }
+}
+
+/// Check if modification time is not after date (second precision)
+fn not_modified_since(last_modified: SystemTime, date: &str) -> Option<bool> {
+    let date = parse_http_date(date)?;
+    let secs = |time: SystemTime| {
+        time.duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or_default()
+    };
+    Some(secs(last_modified) <= secs(date))
+}
+
+/// Conditional request headers
+#[derive(Clone, Debug, Default)]
+pub struct Conditions {
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+    pub if_modified_since: Option<String>,
+    pub if_unmodified_since: Option<String>,
+}
+
+impl Conditions {
+    /// Get conditional headers of request
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let header = |name: &str| req.headers().get(name).map(|value| value.to_string());
+        Self {
+            if_match: header("if-match"),
+            if_none_match: header("if-none-match"),
+            if_modified_since: header("if-modified-since"),
+            if_unmodified_since: header("if-unmodified-since"),
+        }
+    }
+
+    /// Check if no conditional headers present
+    pub fn is_empty(&self) -> bool {
+        self.if_match.is_none()
+            && self.if_none_match.is_none()
+            && self.if_modified_since.is_none()
+            && self.if_unmodified_since.is_none()
+    }
+
+    /// Evaluate against current validators, returns 304 or 412 if the request should not proceed
+    ///
+    /// Can be used by handlers before changing state (e.g. If-Match for PUT)
+    pub fn evaluate(
+        &self,
+        method: &HttpMethod,
+        etag: Option<&str>,
+        last_modified: Option<SystemTime>,
+    ) -> Option<StatusCode> {
+        let safe = *method == HttpMethod::GET || *method == HttpMethod::HEAD;
+
+        // If-Match or If-Unmodified-Since
+        if let Some(if_match) = &self.if_match {
+            if !etag_list_matches(if_match, etag, false) {
+                return Some(StatusCode::PRECONDITION_FAILED);
+            }
+        } else if let (Some(date), Some(last_modified)) = (&self.if_unmodified_since, last_modified)
+        {
+            if not_modified_since(last_modified, date) == Some(false) {
+                return Some(StatusCode::PRECONDITION_FAILED);
+            }
+        }
+
+        // If-None-Match or If-Modified-Since
+        if let Some(if_none_match) = &self.if_none_match {
+            if etag_list_matches(if_none_match, etag, true) {
+                return match safe {
+                    true => Some(StatusCode::NOT_MODIFIED),
+                    false => Some(StatusCode::PRECONDITION_FAILED),
+                };
+            }
+        } else if let (true, Some(date), Some(last_modified)) =
+            (safe, &self.if_modified_since, last_modified)
+        {
+            if not_modified_since(last_modified, date) == Some(true) {
+                return Some(StatusCode::NOT_MODIFIED);
+            }
+        }
+
+        // proceed
+        None
+    }
+
+    /// Evaluate against ETag and Last-Modified headers of 2xx response
+    ///
+    /// Creates 304 responses with validators or 412 responses
+    pub fn apply(&self, method: &HttpMethod, response: Response) -> Response {
+        // only successful responses
+        if self.is_empty() || !response.status().is_success() {
+            return response;
+        }
+
+        // evaluate validators
+        let last_modified = response.header("last-modified").and_then(parse_http_date);
+        match self.evaluate(method, response.header("etag"), last_modified) {
+            Some(StatusCode::NOT_MODIFIED) => {
+                let mut not_modified = Response::new().set_status(StatusCode::NOT_MODIFIED);
+                for (name, value) in response.headers().iter() {
+                    if NOT_MODIFIED_HEADERS.contains(&name.to_lowercase().as_str()) {
+                        not_modified = not_modified.add_header(name, value);
+                    }
+                }
+                not_modified
+            }
+            Some(status) => error_response("Precondition failed", status),
+            None => response,
+        }
+    }
+}
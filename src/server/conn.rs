@@ -2,23 +2,29 @@
 
 use crate::{
     name,
-    server::{respond, Handler, HttpRequest, HttpSettings, ResponseData, Stream},
+    server::{
+        apply_range, BodyReader, BodyState, Conditions, Handler, HttpMethod, HttpRequest,
+        HttpSettings, Response, StatusCode, Stream, TlsStream, BODY_TOO_LARGE,
+    },
     version,
 };
+use kern::byte::scan;
 use kern::Fail;
-use rustls::{ServerConfig, ServerSession, Stream as RustlsStream};
-use std::io::prelude::{Read, Write};
+use rustls::{ServerConfig, ServerSession};
+use std::fmt::Display;
+use std::io::prelude::Read;
+use std::io::{copy, sink, ErrorKind};
+use std::mem::take;
 use std::net::{TcpListener, TcpStream};
 use std::sync::{Arc, RwLock};
 use std::thread;
 
-/// Accept connections
-pub fn accept_connections<T: Send + Sync + 'static>(
+/// Accept connections (plain TCP if no TLS config)
+pub fn accept_connections(
     listener: Arc<RwLock<TcpListener>>,
     http_settings: Arc<HttpSettings>,
-    tls_config: Arc<ServerConfig>,
-    handler: Handler<T>,
-    shared: Arc<RwLock<T>>,
+    tls_config: Option<Arc<ServerConfig>>,
+    handler: Arc<dyn Handler>,
 ) {
     loop {
         // accept connection
@@ -26,24 +32,23 @@ pub fn accept_connections<T: Send + Sync + 'static>(
             // clones
             let http_settings = http_settings.clone();
             let tls_config = tls_config.clone();
-            let shared = shared.clone();
+            let handler = handler.clone();
 
             // spawn new thread
             thread::spawn(move || {
                 // handle connection
-                handle_connection(stream, &http_settings, tls_config, handler, shared).ok();
+                handle_connection(stream, &http_settings, tls_config, &*handler).ok();
             });
         }
     }
 }
 
-/// Handle connection
-pub fn handle_connection<T: Send + Sync + 'static>(
+/// Handle connection (plain TCP if no TLS config)
+pub fn handle_connection(
     mut stream: TcpStream,
     http_settings: &HttpSettings,
-    tls_config: Arc<ServerConfig>,
-    handler: Handler<T>,
-    shared: Arc<RwLock<T>>,
+    tls_config: Option<Arc<ServerConfig>>,
+    handler: &dyn Handler,
 ) -> Result<(), Fail> {
     // set timeouts
     stream
@@ -53,107 +58,251 @@ pub fn handle_connection<T: Send + Sync + 'static>(
         .set_write_timeout(http_settings.write_timeout)
         .or_else(Fail::from)?;
 
-    // create TLS connection
-    let mut session = ServerSession::new(&tls_config);
-    let mut stream = RustlsStream::new(&mut session, &mut stream);
-
-    // read header
-    let response = match read_header(&mut stream, http_settings) {
-        Ok((header, rest)) => {
-            // parse HTTP request and process
-            let http_request = HttpRequest::from(&header, rest, &mut stream, http_settings);
-            match handler(http_request, shared) {
-                Ok(response) => response,
-                Err(err) => respond(
-                    format!("<!DOCTYPE html><html><head><title>{0}</title></head><body><h3>HTTP server error</h3><p>{0}</p><hr><address>{1} v{2}</address></body></html>", err, name(), version()),
-                    "text/html",
-                    Some(ResponseData::new().set_status("400 Bad Request"))),
-            }
+    // create TLS connection or use plain TCP
+    match tls_config {
+        Some(tls_config) => serve_connection(
+            &mut TlsStream::new(ServerSession::new(&tls_config), stream),
+            http_settings,
+            handler,
+        ),
+        None => serve_connection(&mut stream, http_settings, handler),
+    }
+}
+
+/// Handle requests on stream
+fn serve_connection<S: Stream>(
+    stream: &mut S,
+    http_settings: &HttpSettings,
+    handler: &dyn Handler,
+) -> Result<(), Fail> {
+    // handle requests in order until connection closed, carry pipelined data
+    let mut pending = Vec::new();
+    for requests in 1..=http_settings.max_requests.max(1) {
+        // wait for next request with idle timeout
+        if requests > 1 {
+            stream
+                .tcp()
+                .set_read_timeout(http_settings.keep_alive_timeout)
+                .or_else(Fail::from)?;
         }
-        Err(err) => {
-            if err.err_msg() == "received corrupt message" {
-                return Fail::from("Not a TLS connection");
+
+        // read header
+        let header = read_header(stream, take(&mut pending), http_settings);
+        stream
+            .tcp()
+            .set_read_timeout(http_settings.read_timeout)
+            .or_else(Fail::from)?;
+        let (mut response, keep_alive, http_1_0, head) = match header {
+            Ok(Some((header, rest))) => {
+                // parse HTTP request
+                let mut body_state = BodyState::new(rest, http_settings.body_buffer);
+                let http_request =
+                    HttpRequest::from(&header, &mut body_state, stream, http_settings);
+                let (keep_alive, http_1_0, head) = match &http_request {
+                    Ok(req) => (
+                        req.keep_alive(),
+                        req.version() == "HTTP/1.0",
+                        *req.method() == HttpMethod::HEAD,
+                    ),
+                    Err(_) => (false, false, false),
+                };
+
+                // keep conditional headers of GET and HEAD requests (handlers check others)
+                let conditions = match &http_request {
+                    Ok(req)
+                        if *req.method() == HttpMethod::GET
+                            || *req.method() == HttpMethod::HEAD =>
+                    {
+                        Some((req.method().clone(), Conditions::from_request(req)))
+                    }
+                    _ => None,
+                };
+
+                // keep range headers of GET requests
+                let range = match &http_request {
+                    Ok(req) if *req.method() == HttpMethod::GET => (
+                        req.headers().get("range").map(|range| range.to_string()),
+                        req.headers()
+                            .get("if-range")
+                            .map(|if_range| if_range.to_string()),
+                    ),
+                    _ => (None, None),
+                };
+
+                // reject extension methods if not allowed
+                let response = match &http_request {
+                    Ok(req)
+                        if req.method().is_extension()
+                            && !http_settings.allow_extension_methods =>
+                    {
+                        error_response(
+                            format!("Method {} not implemented", req.method()),
+                            StatusCode::NOT_IMPLEMENTED,
+                        )
+                    }
+                    // reject unsupported content encodings
+                    Ok(req) if req.content_coding().is_err() => error_response(
+                        req.content_coding()
+                            .err()
+                            .map(|err| err.to_string())
+                            .unwrap_or_default(),
+                        StatusCode::UNSUPPORTED_MEDIA_TYPE,
+                    )
+                    .set_header("accept-encoding", "gzip, deflate"),
+                    // process
+                    _ => match handler.handle(http_request) {
+                        Ok(response) => response,
+                        Err(err) if err.err_msg() == BODY_TOO_LARGE => {
+                            error_response(err, StatusCode::CONTENT_TOO_LARGE)
+                        }
+                        Err(err) => error_response(err, StatusCode::BAD_REQUEST),
+                    },
+                };
+                let response = match conditions {
+                    Some((method, conditions)) => conditions.apply(&method, response),
+                    None => response,
+                };
+                let response = apply_range(response, range.0.as_deref(), range.1.as_deref());
+
+                // drain unread body and keep pipelined data
+                let keep_alive = keep_alive && drain_body(stream, &mut body_state, http_settings);
+                pending = body_state.take_input();
+                (response, keep_alive, http_1_0, head)
+            }
+            Ok(None) => break,
+            Err(err) => {
+                if err.err_msg() == "received corrupt message" {
+                    return Fail::from("Not a TLS connection");
+                }
+                (
+                    error_response(err, StatusCode::BAD_REQUEST),
+                    false,
+                    false,
+                    false,
+                )
             }
-            respond(
-            format!("<!DOCTYPE html><html><head><title>{0}</title></head><body><h3>HTTP server error</h3><p>{0}</p><hr><address>{1} v{2}</address></body></html>", err, name(), version()),
-            "text/html",
-            Some(ResponseData::new().set_status("400 Bad Request")),
-        )
+        };
+
+        // set connection header (close-delimited body for HTTP/1.0 if length unknown)
+        let keep_alive = keep_alive
+            && requests < http_settings.max_requests
+            && !(http_1_0 && !head && response.body().len().is_none())
+            && !response
+                .header("connection")
+                .map(|connection| connection.eq_ignore_ascii_case("close"))
+                .unwrap_or(false);
+        if !keep_alive {
+            response = response.set_header("connection", "close");
+        } else if http_1_0 {
+            response = response.set_header("connection", "keep-alive");
         }
-    };
 
-    // respond
-    stream.write_all(&response).or_else(Fail::from)?;
-    stream.flush().or_else(Fail::from)?;
+        // respond (without body for HEAD requests)
+        response.write(stream, !head, !http_1_0)?;
+
+        // close connection
+        if !keep_alive {
+            break;
+        }
+    }
 
     // done
     Ok(())
 }
 
-/// Read until \r\n\r\n
+/// Read and discard unread body, returns false if body is too large or broken
+fn drain_body(
+    stream: &mut impl Stream,
+    body_state: &mut BodyState,
+    http_settings: &HttpSettings,
+) -> bool {
+    // check if already read
+    if body_state.is_done() {
+        return true;
+    }
+
+    // read up to max body size
+    let limit = http_settings.max_body_size as u64;
+    let mut reader = BodyReader::new(stream, body_state).take(limit);
+    copy(&mut reader, &mut sink()).is_ok() && body_state.is_done()
+}
+
+/// Create HTML error response
+pub(crate) fn error_response(err: impl Display, status: StatusCode) -> Response {
+    // escape error message
+    let err = escape_html(&err.to_string());
+
+    // create response
+    Response::new()
+        .set_status(status)
+        .set_content_type("text/html; charset=utf-8")
+        .set_body(format!("<!DOCTYPE html><html><head><title>{0}</title></head><body><h3>HTTP server error</h3><p>{0}</p><hr><address>{1} v{2}</address></body></html>", err, name(), version()))
+}
+
+/// Create 500 response for handler response that could not be converted
+pub(crate) fn invalid_response(err: impl Display) -> Response {
+    error_response(
+        format!("Invalid handler response: {}", err),
+        StatusCode::INTERNAL_SERVER_ERROR,
+    )
+}
+
+/// Escape HTML special characters
+pub(crate) fn escape_html(raw: &str) -> String {
+    raw.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Read until \r\n\r\n starting with pending bytes (None if connection closed or idle before request)
 fn read_header(
-    stream: &mut Stream,
+    stream: &mut impl Stream,
+    pending: Vec<u8>,
     http_settings: &HttpSettings,
-) -> Result<(String, Vec<u8>), Fail> {
-    // initialize vectors
-    let mut header = Vec::new();
-    let mut rest = Vec::new();
+) -> Result<Option<(String, Vec<u8>)>, Fail> {
+    // initialize vectors, ignore empty lines before request
+    let skip = pending
+        .iter()
+        .position(|&b| b != b'\r' && b != b'\n')
+        .unwrap_or(pending.len());
+    let mut header = pending[skip..].to_vec();
     let mut buf = vec![0u8; http_settings.header_buffer];
 
     // read continously
+    let mut searched = 0usize;
     let mut read_fails = 0;
-    'l: loop {
-        // read from stream and check max header size
-        let length = stream.read(&mut buf).or_else(Fail::from)?;
-        if header.len() + length > http_settings.max_header_size {
-            return Fail::from("Max header size exceeded");
-        }
+    loop {
+        // check if header end reached (including previously read bytes)
+        let start = searched.saturating_sub(3);
+        if let Some(pos) = scan(&header[start..], b"\r\n\r\n") {
+            // split into header and rest
+            let rest = header.split_off(start + pos + 4);
 
-        // only use actually read data
-        let buf = &buf[0..length];
-
-        // iterate through bytes
-        'f: for (i, &b) in buf.iter().enumerate() {
-            // check if byte is \r
-            if b == b'\r' {
-                // check if necessary to read 3 more bytes
-                if buf.len() < i + 4 {
-                    // read 3 more bytes
-                    let mut buf_temp = vec![0u8; i + 4 - buf.len()];
-                    stream.read(&mut buf_temp).or_else(Fail::from)?;
-
-                    // combine buffers and compare bytes
-                    let mut buf2 = [&buf[..], &buf_temp[..]].concat();
-                    let header_end =
-                        buf2[i + 1] == b'\n' && buf2[i + 2] == b'\r' && buf2[i + 3] == b'\n';
-
-                    // add buffer to header and check if header end reached
-                    header.append(&mut buf2);
-                    if header_end {
-                        // header end reached
-                        break 'l;
-                    } else {
-                        // not yet, read more
-                        break 'f;
-                    }
-                // can read 3 more bytes, so compare
-                } else if buf[i + 1] == b'\n' && buf[i + 2] == b'\r' && buf[i + 3] == b'\n' {
-                    // split into header and rest
-                    let (split1, split2) = buf.split_at(i + 4);
-                    header.extend_from_slice(split1);
-                    rest.extend_from_slice(split2);
-
-                    // header end reached
-                    break 'l;
-                }
-            }
+            // return header as string and rest
+            return match String::from_utf8(header) {
+                Ok(header) => Ok(Some((header, rest))),
+                Err(err) => Fail::from(err),
+            };
+        }
+        searched = header.len();
 
-            // last byte reached, but end not reached yet
-            if buf.len() == i + 1 {
-                // add buffer to header
-                header.extend_from_slice(&buf);
+        // read from stream and check max header size
+        let length = match stream.read(&mut buf) {
+            Ok(0) if header.is_empty() => return Ok(None),
+            Ok(0) => return Fail::from("Connection closed before end of header"),
+            Err(err)
+                if header.is_empty()
+                    && matches!(err.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                return Ok(None)
             }
+            result => result.or_else(Fail::from)?,
+        };
+        if header.len() + length > http_settings.max_header_size {
+            return Fail::from("Max header size exceeded");
         }
+        header.extend_from_slice(&buf[..length]);
 
         // check if didn't read fully
         if length < http_settings.header_buffer {
@@ -165,13 +314,4 @@ fn read_header(
             }
         }
     }
-
-    // return header as string and rest
-    Ok((
-        match String::from_utf8(header) {
-            Ok(header) => header,
-            Err(err) => return Fail::from(err),
-        },
-        rest,
-    ))
 }
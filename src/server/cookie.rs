@@ -0,0 +1,201 @@
+//! Cookies
+
+use crate::common::http_date;
+use crate::server::{is_token, Parameters};
+use kern::Fail;
+use std::fmt::{Display, Formatter, Result as FmtResult};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// SameSite cookie attribute
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SameSite {
+    Strict,
+    Lax,
+    None,
+}
+
+impl SameSite {
+    /// Get attribute value
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Strict => "Strict",
+            Self::Lax => "Lax",
+            Self::None => "None",
+        }
+    }
+}
+
+/// Cookie for Set-Cookie header
+#[derive(Clone, Debug, PartialEq)]
+pub struct Cookie {
+    name: String,
+    value: String,
+    path: Option<String>,
+    domain: Option<String>,
+    max_age: Option<u64>,
+    expires: Option<SystemTime>,
+    secure: bool,
+    http_only: bool,
+    same_site: Option<SameSite>,
+}
+
+impl Cookie {
+    /// Create cookie (name must be a token, value must not contain spaces, quotes, commas, semicolons or backslashes)
+    pub fn new(name: impl Into<String>, value: impl Into<String>) -> Result<Self, Fail> {
+        // check name and value
+        let (name, value) = (name.into(), value.into());
+        if !is_token(&name) {
+            return Fail::from("Invalid cookie name");
+        } else if !is_cookie_value(&value) {
+            return Fail::from("Invalid cookie value");
+        }
+
+        // create cookie
+        Ok(Self {
+            name,
+            value,
+            path: None,
+            domain: None,
+            max_age: None,
+            expires: None,
+            secure: false,
+            http_only: false,
+            same_site: None,
+        })
+    }
+
+    /// Create cookie removing existing cookie with name (path and domain must match)
+    pub fn removal(name: impl Into<String>) -> Result<Self, Fail> {
+        Ok(Self::new(name, "")?
+            .set_max_age(Some(0))
+            .set_expires(Some(UNIX_EPOCH)))
+    }
+
+    /// Set Path attribute (semicolons and control characters are removed)
+    pub fn set_path(mut self, path: Option<&str>) -> Self {
+        self.path = path.map(attribute_value);
+        self
+    }
+
+    /// Set Domain attribute (semicolons and control characters are removed)
+    pub fn set_domain(mut self, domain: Option<&str>) -> Self {
+        self.domain = domain.map(attribute_value);
+        self
+    }
+
+    /// Set Max-Age attribute in seconds
+    pub fn set_max_age(mut self, max_age: Option<u64>) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Set Expires attribute
+    pub fn set_expires(mut self, expires: Option<SystemTime>) -> Self {
+        self.expires = expires;
+        self
+    }
+
+    /// Set Secure attribute
+    pub fn set_secure(mut self, secure: bool) -> Self {
+        self.secure = secure;
+        self
+    }
+
+    /// Set HttpOnly attribute
+    pub fn set_http_only(mut self, http_only: bool) -> Self {
+        self.http_only = http_only;
+        self
+    }
+
+    /// Set SameSite attribute (SameSite=None requires Secure in browsers)
+    pub fn set_same_site(mut self, same_site: Option<SameSite>) -> Self {
+        self.same_site = same_site;
+        self
+    }
+
+    /// Get name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get value
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    /// Get Set-Cookie header value
+    pub fn to_header_value(&self) -> String {
+        self.to_string()
+    }
+}
+
+impl Display for Cookie {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        // name and value
+        write!(formatter, "{}={}", self.name, self.value)?;
+
+        // optional attributes
+        if let Some(path) = &self.path {
+            write!(formatter, "; Path={}", path)?;
+        }
+        if let Some(domain) = &self.domain {
+            write!(formatter, "; Domain={}", domain)?;
+        }
+        if let Some(max_age) = self.max_age {
+            write!(formatter, "; Max-Age={}", max_age)?;
+        }
+        if let Some(expires) = self.expires {
+            write!(formatter, "; Expires={}", http_date(expires))?;
+        }
+        if self.secure {
+            write!(formatter, "; Secure")?;
+        }
+        if self.http_only {
+            write!(formatter, "; HttpOnly")?;
+        }
+        if let Some(same_site) = self.same_site {
+            write!(formatter, "; SameSite={}", same_site.as_str())?;
+        }
+
+        // return success
+        Ok(())
+    }
+}
+
+/// Parse Cookie header into name-value pairs (malformed pairs are skipped)
+pub fn parse_cookies(header: &str) -> Parameters<String> {
+    // iterate through name-value pairs
+    let mut cookies = Parameters::new();
+    for pair in header.split(';') {
+        if let Some((name, value)) = pair.split_once('=') {
+            // remove optional quotes
+            let (name, value) = (name.trim(), value.trim());
+            let value = value
+                .strip_prefix('"')
+                .and_then(|value| value.strip_suffix('"'))
+                .unwrap_or(value);
+            // add pair with valid name
+            if is_token(name) {
+                cookies.add(name, value.to_string());
+            }
+        }
+    }
+
+    // return cookies
+    cookies
+}
+
+/// Check if valid cookie value (cookie-octets)
+fn is_cookie_value(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|b| (0x21..=0x7e).contains(&b) && !b"\",;\\".contains(&b))
+}
+
+/// Remove characters not allowed in attribute values
+fn attribute_value(value: &str) -> String {
+    value
+        .chars()
+        .filter(|&c| c != ';' && !c.is_control())
+        .collect()
+}
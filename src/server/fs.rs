@@ -0,0 +1,281 @@
+//! Static file serving
+
+use crate::common::{http_date, percent_encode};
+use crate::server::{
+    error_response, escape_html, file_etag, is_text_type, Handler, HttpMethod, HttpRequest,
+    Response, StatusCode,
+};
+use kern::Fail;
+use std::fs::{self, File};
+use std::io::{Error, ErrorKind};
+use std::path::{Path, PathBuf};
+
+/// Static file service for a directory root
+///
+/// Serves the `path` parameter if routed (`/static/*path`) or the request path otherwise
+#[derive(Clone, Debug)]
+pub struct FileServer {
+    root: PathBuf,
+    index_files: Vec<String>,
+    listing: bool,
+    charset: Option<String>,
+}
+
+impl FileServer {
+    /// Create file server for directory root
+    pub fn new(root: impl AsRef<Path>) -> Result<Self, Fail> {
+        // resolve root
+        let root = root.as_ref().canonicalize().or_else(Fail::from)?;
+        if !root.is_dir() {
+            return Fail::from("File server root is not a directory");
+        }
+
+        // default settings
+        Ok(Self {
+            root,
+            index_files: vec!["index.html".to_string()],
+            listing: false,
+            charset: Some("utf-8".to_string()),
+        })
+    }
+
+    /// Set index files tried for directories (in order)
+    pub fn set_index_files(mut self, index_files: &[&str]) -> Self {
+        self.index_files = index_files.iter().map(|file| file.to_string()).collect();
+        self
+    }
+
+    /// Enable or disable directory listings
+    pub fn set_listing(mut self, listing: bool) -> Self {
+        self.listing = listing;
+        self
+    }
+
+    /// Set charset added to text content types (None to omit)
+    pub fn set_charset(mut self, charset: Option<&str>) -> Self {
+        self.charset = charset.map(|charset| charset.to_string());
+        self
+    }
+
+    /// Get directory root
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Serve decoded path relative to root
+    pub fn serve(&self, req: &HttpRequest, path: &str) -> Response {
+        // only GET and HEAD
+        if *req.method() != HttpMethod::GET && *req.method() != HttpMethod::HEAD {
+            return error_response(
+                format!("Method {} not allowed", req.method()),
+                StatusCode::METHOD_NOT_ALLOWED,
+            )
+            .set_header("allow", "GET, HEAD");
+        }
+
+        // resolve path safely
+        let file_path = match self.resolve(path) {
+            Ok(file_path) => file_path,
+            Err(err) => return io_error_response(err, path),
+        };
+
+        // serve directory
+        if file_path.is_dir() {
+            // redirect to path with trailing slash (relative links, never to another host)
+            if !req.url().ends_with('/') {
+                let segment = req.url().rsplit('/').next().unwrap_or_default();
+                let mut location = format!("./{}/", segment);
+                if !req.query().is_empty() {
+                    location.push('?');
+                    location.push_str(req.query());
+                }
+                return Response::new()
+                    .set_status(StatusCode::MOVED_PERMANENTLY)
+                    .set_header("location", location);
+            }
+
+            // try index files
+            for index_file in &self.index_files {
+                let index = format!("{}/{}", path.trim_end_matches('/'), index_file);
+                if let Ok(index_path) = self.resolve(&index) {
+                    if index_path.is_file() {
+                        return self.serve_file(&index_path, &index);
+                    }
+                }
+            }
+
+            // list directory
+            return match self.listing {
+                true => self.list_directory(&file_path, req.path()),
+                false => error_response(
+                    format!("Directory listing for {} not allowed", path),
+                    StatusCode::FORBIDDEN,
+                ),
+            };
+        }
+
+        // serve file
+        self.serve_file(&file_path, path)
+    }
+
+    /// Resolve decoded path within root (follows symlinks, denies escapes)
+    pub fn resolve(&self, path: &str) -> Result<PathBuf, Error> {
+        // build path from normal segments only
+        let mut file_path = self.root.clone();
+        for segment in path.split('/') {
+            match segment {
+                "" | "." => {}
+                ".." => return Err(Error::new(ErrorKind::PermissionDenied, "Path traversal")),
+                _ if segment.contains(&['\\', '\0', ':'][..]) => {
+                    return Err(Error::new(ErrorKind::PermissionDenied, "Invalid path"))
+                }
+                _ => file_path.push(segment),
+            }
+        }
+
+        // resolve symlinks and check if still within root
+        let file_path = file_path.canonicalize()?;
+        match file_path.starts_with(&self.root) {
+            true => Ok(file_path),
+            false => Err(Error::new(ErrorKind::PermissionDenied, "Path outside root")),
+        }
+    }
+
+    /// Create streaming response for file (path is used in error messages)
+    fn serve_file(&self, file_path: &Path, path: &str) -> Response {
+        // open file and check type
+        let (file, metadata) = match File::open(file_path).and_then(|file| {
+            let metadata = file.metadata()?;
+            Ok((file, metadata))
+        }) {
+            Ok(file) => file,
+            Err(err) => return io_error_response(err, path),
+        };
+        if !metadata.is_file() {
+            return error_response("Not a regular file", StatusCode::FORBIDDEN);
+        }
+
+        // content type with charset for text files
+        let mut content_type = mime_type(file_path).to_string();
+        if let Some(charset) = &self.charset {
+            if is_text_type(&content_type) {
+                content_type = format!("{}; charset={}", content_type, charset);
+            }
+        }
+
+        // stream file with validators
+        let mut response = Response::new()
+            .set_content_type(content_type)
+            .set_header("etag", file_etag(&metadata));
+        if let Ok(modified) = metadata.modified() {
+            response = response.set_header("last-modified", http_date(modified));
+        }
+        response.set_body_seekable(file, metadata.len())
+    }
+
+    /// Create HTML directory listing
+    fn list_directory(&self, dir_path: &Path, url_path: &str) -> Response {
+        // read sorted entries
+        let mut entries = match fs::read_dir(dir_path).and_then(|entries| {
+            entries
+                .map(|entry| {
+                    let entry = entry?;
+                    let name = entry.file_name().to_string_lossy().to_string();
+                    Ok((name, entry.path().is_dir()))
+                })
+                .collect::<Result<Vec<_>, Error>>()
+        }) {
+            Ok(entries) => entries,
+            Err(err) => return io_error_response(err, url_path),
+        };
+        entries.sort();
+
+        // create list
+        let mut list = String::new();
+        if dir_path != self.root {
+            list.push_str("<li><a href=\"../\">../</a></li>");
+        }
+        for (name, is_dir) in entries {
+            let suffix = if is_dir { "/" } else { "" };
+            list.push_str(&format!(
+                "<li><a href=\"{}{2}\">{}{2}</a></li>",
+                percent_encode(&name),
+                escape_html(&name),
+                suffix
+            ));
+        }
+
+        // create response
+        let title = escape_html(&format!("Index of {}", url_path));
+        Response::new()
+            .set_content_type("text/html; charset=utf-8")
+            .set_body(format!("<!DOCTYPE html><html><head><title>{0}</title></head><body><h3>{0}</h3><ul>{1}</ul></body></html>", title, list))
+    }
+}
+
+impl Handler for FileServer {
+    fn handle(&self, req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+        let req = req?;
+        let path = req.param("path").unwrap_or_else(|| req.path());
+        Ok(self.serve(&req, path))
+    }
+}
+
+/// Map IO error to 404, 403 or 500 response (without OS error details)
+fn io_error_response(err: Error, path: &str) -> Response {
+    match err.kind() {
+        ErrorKind::NotFound => error_response(format!("{} not found", path), StatusCode::NOT_FOUND),
+        ErrorKind::PermissionDenied => {
+            error_response(format!("Access to {} denied", path), StatusCode::FORBIDDEN)
+        }
+        _ => error_response(
+            format!("Failed to read {}", path),
+            StatusCode::INTERNAL_SERVER_ERROR,
+        ),
+    }
+}
+
+/// Get MIME type by file extension (application/octet-stream if unknown)
+pub fn mime_type(path: impl AsRef<Path>) -> &'static str {
+    // get lowercase extension
+    let extension = path
+        .as_ref()
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    // match extension
+    match extension.as_str() {
+        "html" | "htm" => "text/html",
+        "css" => "text/css",
+        "js" | "mjs" => "application/javascript",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "txt" | "text" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "svg" => "image/svg+xml",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "ico" => "image/x-icon",
+        "bmp" => "image/bmp",
+        "avif" => "image/avif",
+        "woff" => "font/woff",
+        "woff2" => "font/woff2",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        "mp3" => "audio/mpeg",
+        "ogg" => "audio/ogg",
+        "wav" => "audio/wav",
+        "mp4" => "video/mp4",
+        "webm" => "video/webm",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "tar" => "application/x-tar",
+        "wasm" => "application/wasm",
+        _ => "application/octet-stream",
+    }
+}
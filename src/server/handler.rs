@@ -0,0 +1,52 @@
+//! Request handlers
+
+use crate::server::{invalid_response, HttpRequest, Response};
+use kern::Fail;
+use std::convert::TryInto;
+use std::fmt::Display;
+use std::sync::{Arc, RwLock};
+
+/// Request handler (implemented for closures taking `Result<HttpRequest, Fail>`)
+///
+/// Closures may return a `Response` or serialized response bytes (invalid bytes are sent as 500)
+pub trait Handler: Send + Sync + 'static {
+    /// Handle request and create response
+    fn handle(&self, req: Result<HttpRequest, Fail>) -> Result<Response, Fail>;
+}
+
+impl<F, R> Handler for F
+where
+    F: Fn(Result<HttpRequest, Fail>) -> Result<R, Fail> + Send + Sync + 'static,
+    R: TryInto<Response>,
+    R::Error: Display,
+{
+    fn handle(&self, req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+        Ok(self(req)?.try_into().unwrap_or_else(invalid_response))
+    }
+}
+
+/// Handler function with shared state
+pub type SharedHandler<T> = fn(Result<HttpRequest, Fail>, Arc<RwLock<T>>) -> Result<Vec<u8>, Fail>;
+
+/// Handler function combined with shared state
+#[derive(Debug)]
+pub struct WithShared<T> {
+    handler: SharedHandler<T>,
+    shared: Arc<RwLock<T>>,
+}
+
+impl<T: Send + Sync + 'static> Handler for WithShared<T> {
+    fn handle(&self, req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+        Ok((self.handler)(req, self.shared.clone())?
+            .try_into()
+            .unwrap_or_else(invalid_response))
+    }
+}
+
+/// Combine handler function with shared state
+pub fn with_shared<T: Send + Sync + 'static>(
+    handler: SharedHandler<T>,
+    shared: Arc<RwLock<T>>,
+) -> WithShared<T> {
+    WithShared { handler, shared }
+}
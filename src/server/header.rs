@@ -0,0 +1,140 @@
+//! HTTP headers
+
+use kern::Fail;
+
+/// Multi-value header map with case-insensitive names preserving order
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct HeaderMap {
+    headers: Vec<(String, String)>,
+}
+
+impl HeaderMap {
+    /// Create empty header map
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append header (keeps existing values with the same name)
+    pub fn append(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), Fail> {
+        // check name and value
+        let (name, value) = (name.into(), value.into());
+        if !is_header_name(&name) {
+            return Fail::from("Invalid header name");
+        } else if !is_header_value(&value) {
+            return Fail::from("Invalid header value");
+        }
+
+        // add header
+        self.headers.push((name, value));
+        Ok(())
+    }
+
+    /// Insert header (replaces existing values with the same name)
+    pub fn insert(
+        &mut self,
+        name: impl Into<String>,
+        value: impl Into<String>,
+    ) -> Result<(), Fail> {
+        // check value before removing existing values
+        let (name, value) = (name.into(), value.into());
+        if !is_header_value(&value) {
+            return Fail::from("Invalid header value");
+        }
+
+        // replace header
+        self.remove(&name);
+        self.append(name, value)
+    }
+
+    /// Remove all values of header, returns true if any were removed
+    pub fn remove(&mut self, name: &str) -> bool {
+        // remove and compare length
+        let length = self.headers.len();
+        self.headers.retain(|(k, _)| !k.eq_ignore_ascii_case(name));
+        self.headers.len() != length
+    }
+
+    /// Get first value of header
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Get all values of header in order
+    pub fn get_all(&self, name: &str) -> Vec<&str> {
+        self.headers
+            .iter()
+            .filter(|(k, _)| k.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+            .collect()
+    }
+
+    /// Check if header exists
+    pub fn contains(&self, name: &str) -> bool {
+        self.headers
+            .iter()
+            .any(|(k, _)| k.eq_ignore_ascii_case(name))
+    }
+
+    /// Iterate through all headers in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.headers.iter().map(|(k, v)| (k.as_str(), v.as_str()))
+    }
+
+    /// Number of headers (including repeated names)
+    pub fn len(&self) -> usize {
+        self.headers.len()
+    }
+
+    /// Check if there are no headers
+    pub fn is_empty(&self) -> bool {
+        self.headers.is_empty()
+    }
+}
+
+impl IntoIterator for HeaderMap {
+    type Item = (String, String);
+    type IntoIter = std::vec::IntoIter<(String, String)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.headers.into_iter()
+    }
+}
+
+/// Check if valid header name (token)
+pub fn is_header_name(name: &str) -> bool {
+    is_token(name)
+}
+
+/// Split header line into lowercase name and trimmed value
+///
+/// None if malformed (no colon or invalid name, e.g. whitespace before colon)
+pub(crate) fn split_header_line(line: &str) -> Option<(String, &str)> {
+    // split and check untrimmed name
+    let (name, value) = line.split_once(':')?;
+    match is_header_name(name) {
+        true => Some((name.to_lowercase(), value.trim())),
+        false => None,
+    }
+}
+
+/// Check if string is a valid token (RFC 7230 tchar)
+pub(crate) fn is_token(raw: &str) -> bool {
+    !raw.is_empty()
+        && raw
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b"!#$%&'*+-.^_`|~".contains(&b))
+}
+
+/// Check if valid header value (no control characters except tab, e.g. CR or LF)
+pub fn is_header_value(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|b| b == b'\t' || (b >= 0x20 && b != 0x7f))
+}
@@ -11,14 +11,34 @@ use std::net::TcpListener;
 use std::sync::{Arc, RwLock};
 use std::thread::{self, JoinHandle};
 
-/// Listen on TCP
-pub fn listen<T: Send + Sync + 'static>(
+/// Listen on TCP with TLS
+pub fn listen<H: Handler>(
     addr: &str,
     threads: u8,
     http_settings: HttpSettings,
     tls_config: ServerConfig,
-    handler: Handler<T>,
-    shared: Arc<RwLock<T>>,
+    handler: H,
+) -> Result<Vec<JoinHandle<()>>, Fail> {
+    spawn_listeners(addr, threads, http_settings, Some(tls_config), handler)
+}
+
+/// Listen on plain TCP without TLS (e.g. behind a TLS-terminating proxy)
+pub fn listen_plain<H: Handler>(
+    addr: &str,
+    threads: u8,
+    http_settings: HttpSettings,
+    handler: H,
+) -> Result<Vec<JoinHandle<()>>, Fail> {
+    spawn_listeners(addr, threads, http_settings, None, handler)
+}
+
+/// Listen on TCP and spawn connection accepting threads
+fn spawn_listeners<H: Handler>(
+    addr: &str,
+    threads: u8,
+    http_settings: HttpSettings,
+    tls_config: Option<ServerConfig>,
+    handler: H,
 ) -> Result<Vec<JoinHandle<()>>, Fail> {
     // listen
     let listener = TcpListener::bind(addr).or_else(Fail::from)?;
@@ -26,7 +46,8 @@ pub fn listen<T: Send + Sync + 'static>(
 
     // config
     let http_settings = Arc::new(http_settings);
-    let tls_config = Arc::new(tls_config);
+    let tls_config = tls_config.map(Arc::new);
+    let handler: Arc<dyn Handler> = Arc::new(handler);
 
     // start threads
     let mut handler_threads = Vec::new();
@@ -35,11 +56,11 @@ pub fn listen<T: Send + Sync + 'static>(
         let listener = listener.clone();
         let http_settings = http_settings.clone();
         let tls_config = tls_config.clone();
-        let shared = shared.clone();
+        let handler = handler.clone();
 
         // spawn thread
         handler_threads.push(thread::spawn(move || {
-            accept_connections(listener, http_settings, tls_config, handler, shared)
+            accept_connections(listener, http_settings, tls_config, handler)
         }));
     });
 
@@ -0,0 +1,68 @@
+//! Middleware around handlers
+
+use crate::server::{invalid_response, Handler, HttpRequest, Response};
+use kern::Fail;
+use std::convert::TryInto;
+use std::fmt::Display;
+
+/// Next layer of a middleware chain
+pub type Next<'a> = &'a dyn Fn(Result<HttpRequest, Fail>) -> Result<Response, Fail>;
+
+/// Middleware layer (implemented for closures taking `Result<HttpRequest, Fail>` and `Next`)
+///
+/// A layer may modify the request, short-circuit by not calling next and post-process the response
+pub trait Middleware: Send + Sync + 'static {
+    /// Process request and response around next layer
+    fn handle(&self, req: Result<HttpRequest, Fail>, next: Next) -> Result<Response, Fail>;
+}
+
+impl<F, R> Middleware for F
+where
+    F: Fn(Result<HttpRequest, Fail>, Next) -> Result<R, Fail> + Send + Sync + 'static,
+    R: TryInto<Response>,
+    R::Error: Display,
+{
+    fn handle(&self, req: Result<HttpRequest, Fail>, next: Next) -> Result<Response, Fail> {
+        Ok(self(req, next)?.try_into().unwrap_or_else(invalid_response))
+    }
+}
+
+/// Handler wrapped in middleware layers
+pub struct Chain {
+    layers: Vec<Box<dyn Middleware>>,
+    handler: Box<dyn Handler>,
+}
+
+impl Chain {
+    /// Create chain around handler
+    pub fn new(handler: impl Handler) -> Self {
+        Self {
+            layers: Vec::new(),
+            handler: Box::new(handler),
+        }
+    }
+
+    /// Add layer (layers added first run first on requests and last on responses)
+    pub fn with(mut self, layer: impl Middleware) -> Self {
+        self.layers.push(Box::new(layer));
+        self
+    }
+
+    /// Call layers in order and handler last
+    fn call(
+        &self,
+        layers: &[Box<dyn Middleware>],
+        req: Result<HttpRequest, Fail>,
+    ) -> Result<Response, Fail> {
+        match layers.split_first() {
+            Some((layer, rest)) => layer.handle(req, &|req| self.call(rest, req)),
+            None => self.handler.handle(req),
+        }
+    }
+}
+
+impl Handler for Chain {
+    fn handle(&self, req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+        self.call(&self.layers, req)
+    }
+}
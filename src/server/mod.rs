@@ -1,27 +1,67 @@
 //! HTTP server
 
+mod body;
+mod chunked;
+mod compress;
+mod conditional;
 mod conn;
+mod cookie;
+pub mod fs;
+mod handler;
+mod header;
 mod listener;
+mod middleware;
+mod multipart;
+mod params;
+mod range;
 mod request;
 mod response;
+pub mod router;
+mod status;
 pub mod unsecure;
 
+pub use body::*;
+pub use chunked::*;
+pub use compress::*;
+pub use conditional::*;
 pub use conn::*;
+pub use cookie::*;
+pub use handler::*;
+pub use header::*;
 pub use listener::*;
+pub use middleware::*;
+pub use multipart::*;
+pub use params::*;
+pub use range::*;
 pub use request::*;
 pub use response::*;
+pub use status::*;
 
-use kern::Fail;
-use rustls::{ServerSession, Stream as RustlsStream};
+use rustls::{ServerSession, StreamOwned};
+use std::io::prelude::{Read, Write};
 use std::net::TcpStream;
-use std::sync::{Arc, RwLock};
 use std::time::Duration;
 
 /// TLS stream
-pub type Stream<'a> = RustlsStream<'a, ServerSession, TcpStream>;
+pub type TlsStream = StreamOwned<ServerSession, TcpStream>;
 
-/// Handler function
-pub type Handler<T> = fn(Result<HttpRequest, Fail>, Arc<RwLock<T>>) -> Result<Vec<u8>, Fail>;
+/// Connection stream (TLS or plain TCP)
+pub trait Stream: Read + Write {
+    /// Get underlying TCP stream
+    fn tcp(&self) -> &TcpStream;
+}
+
+impl Stream for TcpStream {
+    fn tcp(&self) -> &TcpStream {
+        self
+    }
+}
+
+impl Stream for TlsStream {
+    fn tcp(&self) -> &TcpStream {
+        &self.sock
+    }
+}
 
 /// HTTP server settings
 #[derive(Clone, Debug, Default)]
@@ -31,9 +71,13 @@ pub struct HttpSettings {
     pub header_buffer: usize,
     pub body_buffer: usize,
     pub header_read_attempts: usize,
-    pub body_read_attempts: usize,
     pub read_timeout: Option<Duration>,
     pub write_timeout: Option<Duration>,
+    pub keep_alive_timeout: Option<Duration>,
+    pub max_requests: usize,
+    pub allow_extension_methods: bool,
+    /// Read whole body before calling handler (otherwise handlers stream or call buffer_body)
+    pub buffer_body: bool,
 }
 
 impl HttpSettings {
@@ -45,9 +89,12 @@ impl HttpSettings {
             header_buffer: 8192,
             body_buffer: 8192,
             header_read_attempts: 3,
-            body_read_attempts: 3,
             read_timeout: Some(Duration::from_secs(10)),
             write_timeout: Some(Duration::from_secs(10)),
+            keep_alive_timeout: Some(Duration::from_secs(5)),
+            max_requests: 100,
+            allow_extension_methods: false,
+            buffer_body: false,
         }
     }
 }
@@ -0,0 +1,513 @@
+//! Multipart form data
+
+use crate::common::percent_decode;
+use crate::server::{split_header_line, HeaderMap};
+use kern::byte::scan;
+use kern::Fail;
+use std::fs::{self, File, OpenOptions};
+use std::io::prelude::{Read, Write};
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Counter for unique temp file names
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Temp file with part data (removed on drop)
+#[derive(Debug)]
+struct SpooledFile {
+    path: PathBuf,
+    size: usize,
+}
+
+impl SpooledFile {
+    /// Create new temp file in directory
+    fn create(dir: &Path) -> Result<(Self, File), Fail> {
+        // unique name
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.subsec_nanos())
+            .unwrap_or_default();
+        let path = dir.join(format!(
+            "lhi-upload-{}-{}-{}",
+            process::id(),
+            nanos,
+            TEMP_COUNTER.fetch_add(1, Ordering::SeqCst)
+        ));
+
+        // create file (only readable by owner on unix)
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(&path).or_else(Fail::from)?;
+        Ok((Self { path, size: 0 }, file))
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        // remove unless persisted
+        if !self.path.as_os_str().is_empty() {
+            fs::remove_file(&self.path).ok();
+        }
+    }
+}
+
+/// Multipart form data part
+#[derive(Clone, Debug, Default)]
+pub struct MultipartPart {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<String>,
+    headers: HeaderMap,
+    data: Vec<u8>,
+    spooled: Option<Arc<SpooledFile>>,
+}
+
+impl MultipartPart {
+    /// Create part from headers (name from Content-Disposition)
+    pub fn from_headers(headers: HeaderMap, data: Vec<u8>) -> Result<Self, Fail> {
+        // parse content disposition
+        let (_, params) = parse_header_value(
+            headers
+                .get("content-disposition")
+                .ok_or_else(|| Fail::new("Missing Content-Disposition in multipart section"))?,
+        );
+        let param = |key: &str| {
+            params
+                .iter()
+                .find(|(k, _)| k == key)
+                .map(|(_, value)| value.to_string())
+        };
+        let name = param("name").ok_or_else(|| Fail::new("Missing name in multipart section"))?;
+
+        // prefer extended filename (e.g. UTF-8''%E2%82%AC.txt)
+        let filename = param("filename*")
+            .and_then(|filename| {
+                let encoded = filename.splitn(3, '\'').nth(2)?;
+                percent_decode(encoded, false)
+                    .ok()
+                    .map(|decoded| String::from_utf8_lossy(&decoded).to_string())
+            })
+            .or_else(|| param("filename"));
+
+        // create part
+        Ok(Self {
+            name,
+            filename,
+            content_type: headers.get("content-type").map(|value| value.to_string()),
+            headers,
+            data,
+            spooled: None,
+        })
+    }
+
+    /// Get field name
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Get filename (only for file uploads)
+    pub fn filename(&self) -> Option<&str> {
+        self.filename.as_deref()
+    }
+
+    /// Get content type
+    pub fn content_type(&self) -> Option<&str> {
+        self.content_type.as_deref()
+    }
+
+    /// Get part headers (lowercase names)
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get part header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Check if part is a file upload
+    pub fn is_file(&self) -> bool {
+        self.filename.is_some()
+    }
+
+    /// Get in-memory data (empty if spooled to temp file)
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Get in-memory data as UTF-8 (lossy)
+    pub fn text(&self) -> String {
+        String::from_utf8_lossy(&self.data).to_string()
+    }
+
+    /// Take in-memory data
+    pub fn into_data(self) -> Vec<u8> {
+        self.data
+    }
+
+    /// Get data length
+    pub fn len(&self) -> usize {
+        match &self.spooled {
+            Some(spooled) => spooled.size,
+            None => self.data.len(),
+        }
+    }
+
+    /// Check if data is empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Check if data is spooled to temp file
+    pub fn is_spooled(&self) -> bool {
+        self.spooled.is_some()
+    }
+
+    /// Get temp file path if spooled (removed when part is dropped)
+    pub fn path(&self) -> Option<&Path> {
+        self.spooled.as_ref().map(|spooled| spooled.path.as_path())
+    }
+
+    /// Get reader over data (in-memory or temp file)
+    pub fn reader(&self) -> Result<Box<dyn Read + '_>, Fail> {
+        match &self.spooled {
+            Some(spooled) => Ok(Box::new(File::open(&spooled.path).or_else(Fail::from)?)),
+            None => Ok(Box::new(&self.data[..])),
+        }
+    }
+
+    /// Save data to path (temp file is moved if possible)
+    pub fn persist(self, path: impl AsRef<Path>) -> Result<(), Fail> {
+        // move, copy or write data
+        let path = path.as_ref();
+        match self.spooled.map(Arc::try_unwrap) {
+            // move temp file or copy if on other file system
+            Some(Ok(mut spooled)) => {
+                if fs::rename(&spooled.path, path).is_err() {
+                    fs::copy(&spooled.path, path).or_else(Fail::from)?;
+                } else {
+                    spooled.path = PathBuf::new();
+                }
+                Ok(())
+            }
+            // copy shared temp file or write in-memory data
+            Some(Err(spooled)) => fs::copy(&spooled.path, path)
+                .map(|_| ())
+                .or_else(Fail::from),
+            None => fs::write(path, &self.data).or_else(Fail::from),
+        }
+    }
+}
+
+/// Multipart reader state
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum ReaderState {
+    Preamble,
+    Delimiter,
+    Done,
+}
+
+/// Streaming multipart form data parser yielding parts one by one
+pub struct MultipartReader<R: Read> {
+    reader: R,
+    delimiter: Vec<u8>,
+    buffer: Vec<u8>,
+    state: ReaderState,
+    spool_threshold: Option<usize>,
+    temp_dir: PathBuf,
+    max_header_size: usize,
+    max_part_size: usize,
+    max_total_size: usize,
+    total: usize,
+}
+
+impl<R: Read> MultipartReader<R> {
+    /// Create reader over body with boundary (in-memory parts without limits)
+    pub fn new(reader: R, boundary: &str) -> Self {
+        Self {
+            reader,
+            delimiter: format!("\r\n--{}", boundary).into_bytes(),
+            // body may start with delimiter without line break
+            buffer: b"\r\n".to_vec(),
+            state: ReaderState::Preamble,
+            spool_threshold: None,
+            temp_dir: std::env::temp_dir(),
+            max_header_size: 8192,
+            max_part_size: usize::MAX,
+            max_total_size: usize::MAX,
+            total: 0,
+        }
+    }
+
+    /// Spool file parts larger than threshold to temp files
+    pub fn set_spool_threshold(mut self, spool_threshold: Option<usize>) -> Self {
+        self.spool_threshold = spool_threshold;
+        self
+    }
+
+    /// Set directory for temp files (default is system temp directory)
+    pub fn set_temp_dir(mut self, temp_dir: impl Into<PathBuf>) -> Self {
+        self.temp_dir = temp_dir.into();
+        self
+    }
+
+    /// Set maximum size of part headers
+    pub fn set_max_header_size(mut self, max_header_size: usize) -> Self {
+        self.max_header_size = max_header_size;
+        self
+    }
+
+    /// Set maximum data size per part
+    pub fn set_max_part_size(mut self, max_part_size: usize) -> Self {
+        self.max_part_size = max_part_size;
+        self
+    }
+
+    /// Set maximum data size of all parts
+    pub fn set_max_total_size(mut self, max_total_size: usize) -> Self {
+        self.max_total_size = max_total_size;
+        self
+    }
+
+    /// Read more input into buffer (false at end of body)
+    fn fill(&mut self) -> Result<bool, Fail> {
+        // read once (retry if interrupted)
+        let mut buf = [0u8; 8192];
+        loop {
+            match self.reader.read(&mut buf) {
+                Ok(length) => {
+                    self.buffer.extend_from_slice(&buf[..length]);
+                    return Ok(length > 0);
+                }
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Fail::from(err),
+            }
+        }
+    }
+
+    /// Read next part (None after last part)
+    pub fn next_part(&mut self) -> Result<Option<MultipartPart>, Fail> {
+        // skip preamble until first delimiter
+        while self.state == ReaderState::Preamble {
+            if let Some(pos) = scan(&self.buffer, &self.delimiter) {
+                self.buffer.drain(..(pos + self.delimiter.len()));
+                self.state = ReaderState::Delimiter;
+            } else {
+                let keep = self.delimiter.len() - 1;
+                if self.buffer.len() > keep {
+                    self.buffer.drain(..(self.buffer.len() - keep));
+                }
+                if !self.fill()? {
+                    return Fail::from("Missing multipart boundary");
+                }
+            }
+        }
+        if self.state == ReaderState::Done {
+            return Ok(None);
+        }
+
+        // end of delimiter line (close delimiter ends body)
+        loop {
+            if self.buffer.starts_with(b"--") {
+                self.state = ReaderState::Done;
+                return Ok(None);
+            }
+            let padding = self
+                .buffer
+                .iter()
+                .take_while(|&&b| b == b' ' || b == b'\t')
+                .count();
+            if self.buffer[padding..].starts_with(b"\r\n") {
+                self.buffer.drain(..(padding + 2));
+                break;
+            } else if self.buffer.len() >= padding + 2 || padding > self.max_header_size {
+                return Fail::from("Broken multipart boundary line");
+            } else if !self.fill()? {
+                return Fail::from("Multipart body not terminated");
+            }
+        }
+
+        // read part headers
+        let headers = loop {
+            if self.buffer.starts_with(b"\r\n") {
+                self.buffer.drain(..2);
+                break HeaderMap::new();
+            } else if let Some(end) = scan(&self.buffer, b"\r\n\r\n") {
+                let headers = parse_part_headers(&self.buffer[..end])?;
+                self.buffer.drain(..(end + 4));
+                break headers;
+            } else if self.buffer.len() > self.max_header_size {
+                return Fail::from("Max multipart header size exceeded");
+            } else if !self.fill()? {
+                return Fail::from("Multipart body not terminated");
+            }
+        };
+        let mut part = MultipartPart::from_headers(headers, Vec::new())?;
+
+        // read data until next delimiter
+        let mut file = None;
+        loop {
+            let (length, found) = match scan(&self.buffer, &self.delimiter) {
+                Some(pos) => (pos, true),
+                None => (
+                    self.buffer.len().saturating_sub(self.delimiter.len() - 1),
+                    false,
+                ),
+            };
+
+            // check limits and add data
+            self.total += length;
+            if part.len() + length > self.max_part_size {
+                return Fail::from("Max multipart part size exceeded");
+            } else if self.total > self.max_total_size {
+                return Fail::from("Max multipart body size exceeded");
+            }
+            self.append(&mut part, &mut file, length)?;
+            self.buffer.drain(..length);
+
+            // end of part
+            if found {
+                self.buffer.drain(..self.delimiter.len());
+                self.state = ReaderState::Delimiter;
+                break;
+            } else if !self.fill()? {
+                return Fail::from("Multipart body not terminated");
+            }
+        }
+
+        // return part
+        if let Some(mut file) = file {
+            file.flush().or_else(Fail::from)?;
+        }
+        Ok(Some(part))
+    }
+
+    /// Append buffered data to part (spools file parts above threshold)
+    fn append(
+        &self,
+        part: &mut MultipartPart,
+        file: &mut Option<File>,
+        length: usize,
+    ) -> Result<(), Fail> {
+        let data = &self.buffer[..length];
+
+        // start spooling
+        if let (Some(threshold), None) = (self.spool_threshold, &part.spooled) {
+            if part.is_file() && part.data.len() + length > threshold {
+                let (mut spooled, mut temp_file) = SpooledFile::create(&self.temp_dir)?;
+                temp_file.write_all(&part.data).or_else(Fail::from)?;
+                spooled.size = part.data.len();
+                part.data = Vec::new();
+                part.spooled = Some(Arc::new(spooled));
+                *file = Some(temp_file);
+            }
+        }
+
+        // write to temp file or memory
+        match (file, &mut part.spooled) {
+            (Some(temp_file), Some(spooled)) => {
+                temp_file.write_all(data).or_else(Fail::from)?;
+                if let Some(spooled) = Arc::get_mut(spooled) {
+                    spooled.size += length;
+                }
+            }
+            _ => part.data.extend_from_slice(data),
+        }
+        Ok(())
+    }
+}
+
+impl<R: Read> Iterator for MultipartReader<R> {
+    type Item = Result<MultipartPart, Fail>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // stop after errors
+        match self.next_part() {
+            Ok(part) => part.map(Ok),
+            Err(err) => {
+                self.state = ReaderState::Done;
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Parse multipart body into parts (same names are preserved in order)
+pub fn parse_multipart(body: &[u8], boundary: &str) -> Result<Vec<MultipartPart>, Fail> {
+    MultipartReader::new(body, boundary).collect()
+}
+
+/// Parse part header lines
+pub(crate) fn parse_part_headers(raw: &[u8]) -> Result<HeaderMap, Fail> {
+    // split lines into name and value
+    let raw = String::from_utf8_lossy(raw);
+    let mut headers = HeaderMap::new();
+    for line in raw.split("\r\n") {
+        let (name, value) =
+            split_header_line(line).ok_or_else(|| Fail::new("Broken multipart section header"))?;
+        headers
+            .append(name, value)
+            .or_else(|_| Fail::from("Broken multipart section header"))?;
+    }
+
+    // return headers
+    Ok(headers)
+}
+
+/// Parse header value with parameters (e.g. `form-data; name="a;b"`), keys are lowercase
+pub fn parse_header_value(value: &str) -> (&str, Vec<(String, String)>) {
+    // split value and parameters
+    let (value, rest) = value.split_once(';').unwrap_or((value, ""));
+    let mut params = Vec::new();
+    let mut chars = rest.chars().peekable();
+
+    // parse parameters
+    loop {
+        // skip separators
+        while chars.peek().is_some_and(|&c| c == ';' || c.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        // read key
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|&c| c != '=' && c != ';') {
+            key.push(c);
+        }
+        let mut param = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            // skip whitespace before value
+            while chars.next_if(|c| c.is_whitespace()).is_some() {}
+
+            // read quoted (backslash escapes quote and backslash) or token value
+            if chars.next_if_eq(&'"').is_some() {
+                while let Some(c) = chars.next() {
+                    match c {
+                        '"' => break,
+                        '\\' if matches!(chars.peek(), Some('"') | Some('\\')) => {
+                            param.extend(chars.next())
+                        }
+                        c => param.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|&c| c != ';') {
+                    param.push(c);
+                }
+                param = param.trim().to_string();
+            }
+        }
+        params.push((key.trim().to_lowercase(), param));
+    }
+
+    // return value and parameters
+    (value.trim(), params)
+}
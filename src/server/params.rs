@@ -0,0 +1,107 @@
+//! Request parameters
+
+use crate::common::percent_decode_utf8;
+use kern::Fail;
+use std::collections::BTreeMap;
+
+/// Multi-value parameters preserving key case and order
+#[derive(Clone, Debug, PartialEq)]
+pub struct Parameters<V> {
+    params: Vec<(String, V)>,
+}
+
+impl<V> Default for Parameters<V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<V> Parameters<V> {
+    /// Create empty parameters
+    pub fn new() -> Self {
+        Self { params: Vec::new() }
+    }
+
+    /// Append parameter (keeps existing values with the same key)
+    pub fn add(&mut self, key: impl Into<String>, value: V) {
+        self.params.push((key.into(), value));
+    }
+
+    /// Get first value of key
+    pub fn get(&self, key: &str) -> Option<&V> {
+        self.params.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+
+    /// Get all values of key in order
+    pub fn get_all(&self, key: &str) -> Vec<&V> {
+        self.params
+            .iter()
+            .filter(|(k, _)| k == key)
+            .map(|(_, v)| v)
+            .collect()
+    }
+
+    /// Check if key exists
+    pub fn contains(&self, key: &str) -> bool {
+        self.params.iter().any(|(k, _)| k == key)
+    }
+
+    /// Iterate through all parameters in order
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &V)> {
+        self.params.iter().map(|(k, v)| (k.as_str(), v))
+    }
+
+    /// Number of parameters (including repeated keys)
+    pub fn len(&self) -> usize {
+        self.params.len()
+    }
+
+    /// Check if there are no parameters
+    pub fn is_empty(&self) -> bool {
+        self.params.is_empty()
+    }
+}
+
+impl<V: Clone> Parameters<V> {
+    /// Single-value map (last value of repeated keys wins)
+    pub fn to_map(&self) -> BTreeMap<String, V> {
+        self.params.iter().cloned().collect()
+    }
+}
+
+/// Parse x-www-form-urlencoded parameters
+pub(crate) fn parse_parameters<'a, V>(
+    raw: &'a str,
+    process_key: fn(&'a str) -> Result<String, Fail>,
+    process_value: fn(&'a str) -> Result<V, Fail>,
+) -> Result<Parameters<V>, Fail> {
+    // parameters
+    let mut params = Parameters::new();
+
+    // split parameters by ampersand
+    for p in raw.split('&').filter(|p| !p.is_empty()) {
+        // split key and value and add to parameters
+        let mut ps = p.splitn(2, '=');
+        params.add(
+            process_key(
+                ps.next()
+                    .ok_or_else(|| Fail::new("broken x-www-form-urlencoded parameters"))?
+                    .trim(), // trimmed key
+            )?,
+            // correct value type
+            process_value(if let Some(value) = ps.next() {
+                value.trim() // trimmed value
+            } else {
+                "" // no value, is option
+            })?,
+        );
+    }
+
+    // return parameters
+    Ok(params)
+}
+
+/// Decode parameter key
+pub(crate) fn decode_key(raw: &str) -> Result<String, Fail> {
+    percent_decode_utf8(raw, true)
+}
@@ -0,0 +1,259 @@
+//! HTTP range requests
+
+use crate::server::{error_response, ReadSeek, Response, ResponseBody, StatusCode};
+use std::collections::VecDeque;
+use std::io::prelude::{Read, Seek};
+use std::io::{Cursor, Error, ErrorKind, Result as IoResult, SeekFrom};
+use std::ops::Range;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Maximum number of ranges per request (header is ignored otherwise)
+const MAX_RANGES: usize = 32;
+
+/// Parse range header for content length
+///
+/// Returns None if the header should be ignored and an empty list if no range is satisfiable
+pub fn parse_range(range: &str, length: u64) -> Option<Vec<Range<u64>>> {
+    // only bytes unit
+    let (unit, specs) = range.split_once('=')?;
+    if !unit.trim().eq_ignore_ascii_case("bytes") {
+        return None;
+    }
+
+    // parse range specs
+    let mut ranges = Vec::new();
+    for (i, spec) in specs.split(',').map(|spec| spec.trim()).enumerate() {
+        if i >= MAX_RANGES {
+            return None;
+        }
+        let (first, last) = spec.split_once('-')?;
+        let range = match (first.trim(), last.trim()) {
+            // suffix range
+            ("", suffix) => {
+                let suffix: u64 = parse_digits(suffix)?;
+                length.saturating_sub(suffix)..length
+            }
+            // open range
+            (first, "") => parse_digits(first)?..length,
+            // closed range
+            (first, last) => {
+                let (first, last) = (parse_digits(first)?, parse_digits(last)?);
+                if last < first {
+                    return None;
+                }
+                first..(last.saturating_add(1)).min(length)
+            }
+        };
+
+        // keep satisfiable ranges
+        if range.start < range.end {
+            ranges.push(range);
+        }
+    }
+
+    // return ranges
+    Some(ranges)
+}
+
+/// Parse non-empty digits only
+fn parse_digits(digits: &str) -> Option<u64> {
+    // reject signs and whitespace accepted by parse
+    match !digits.is_empty() && digits.bytes().all(|b| b.is_ascii_digit()) {
+        true => digits.parse().ok(),
+        false => None,
+    }
+}
+
+/// Check if If-Range validator matches response (strong ETag or exact Last-Modified)
+fn if_range_matches(response: &Response, if_range: &str) -> bool {
+    // compare ETag or date
+    let if_range = if_range.trim();
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        match response.header("etag") {
+            Some(etag) => {
+                !if_range.starts_with("W/") && !etag.starts_with("W/") && etag == if_range
+            }
+            None => false,
+        }
+    } else {
+        response.header("last-modified") == Some(if_range)
+    }
+}
+
+/// Apply range request to 200 response with seekable body (adds accept-ranges)
+///
+/// Creates 206 (multipart/byteranges for multiple ranges) or 416 responses
+pub fn apply_range(
+    mut response: Response,
+    range: Option<&str>,
+    if_range: Option<&str>,
+) -> Response {
+    // only successful seekable responses
+    if response.status() != StatusCode::OK
+        || !matches!(response.body(), ResponseBody::Seekable(_, _))
+    {
+        return response;
+    }
+    response = response.set_header("accept-ranges", "bytes");
+
+    // check range and validator
+    let range = match range {
+        Some(range) if if_range.is_none_or(|if_range| if_range_matches(&response, if_range)) => {
+            range
+        }
+        _ => return response,
+    };
+    let (mut reader, length) = match response.take_body() {
+        ResponseBody::Seekable(reader, length) => (reader, length),
+        _ => unreachable!(),
+    };
+
+    // ranges are relative to current position of body
+    let (ranges, base) = match (parse_range(range, length), reader.stream_position()) {
+        (Some(ranges), Ok(base)) => (ranges, base),
+        _ => return response.set_response_body(ResponseBody::Seekable(reader, length)),
+    };
+
+    // range not satisfiable
+    if ranges.is_empty() {
+        return error_response(
+            "Requested range not satisfiable",
+            StatusCode::RANGE_NOT_SATISFIABLE,
+        )
+        .set_header("content-range", format!("bytes */{}", length));
+    }
+
+    // single range
+    response = response.set_status(StatusCode::PARTIAL_CONTENT);
+    if ranges.len() == 1 {
+        let range = &ranges[0];
+        let content_range = format!("bytes {}-{}/{}", range.start, range.end - 1, length);
+        let body_length = range.end - range.start;
+        let reader = RangeReader::new(reader, base, vec![Section::Range(range.clone())]);
+        return response
+            .set_header("content-range", content_range)
+            .set_body_reader(reader, Some(body_length));
+    }
+
+    // multiple ranges as multipart/byteranges
+    let boundary = boundary();
+    let content_type = response
+        .header("content-type")
+        .map(|value| value.to_string());
+    let mut sections = Vec::new();
+    for range in ranges {
+        let mut part_header = format!("\r\n--{}\r\n", boundary);
+        if let Some(content_type) = &content_type {
+            part_header.push_str(&format!("content-type: {}\r\n", content_type));
+        }
+        part_header.push_str(&format!(
+            "content-range: bytes {}-{}/{}\r\n\r\n",
+            range.start,
+            range.end - 1,
+            length
+        ));
+        sections.push(Section::Bytes(Cursor::new(part_header.into_bytes())));
+        sections.push(Section::Range(range));
+    }
+    sections.push(Section::Bytes(Cursor::new(
+        format!("\r\n--{}--\r\n", boundary).into_bytes(),
+    )));
+    let body_length = sections.iter().map(|section| section.len()).sum();
+    response
+        .set_content_type(format!("multipart/byteranges; boundary={}", boundary))
+        .set_body_reader(RangeReader::new(reader, base, sections), Some(body_length))
+}
+
+/// Create multipart boundary
+fn boundary() -> String {
+    // unique by time
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_nanos())
+        .unwrap_or_default();
+    format!("lhi{:x}", nanos)
+}
+
+/// Range response body section
+enum Section {
+    Bytes(Cursor<Vec<u8>>),
+    Range(Range<u64>),
+}
+
+impl Section {
+    /// Get section length
+    fn len(&self) -> u64 {
+        match self {
+            Self::Bytes(bytes) => bytes.get_ref().len() as u64,
+            Self::Range(range) => range.end - range.start,
+        }
+    }
+}
+
+/// Reader over ranges of a seekable source and in-memory sections
+struct RangeReader {
+    source: Box<dyn ReadSeek>,
+    base: u64,
+    sections: VecDeque<Section>,
+    remaining: Option<u64>,
+}
+
+impl RangeReader {
+    /// Create new reader (ranges start at base offset of source)
+    fn new(source: Box<dyn ReadSeek>, base: u64, sections: Vec<Section>) -> Self {
+        Self {
+            source,
+            base,
+            sections: sections.into(),
+            remaining: None,
+        }
+    }
+}
+
+impl Read for RangeReader {
+    fn read(&mut self, buf: &mut [u8]) -> IoResult<usize> {
+        // nothing to read into
+        if buf.is_empty() {
+            return Ok(0);
+        }
+
+        // read until data or end of sections
+        loop {
+            // read current section
+            let length = match self.sections.front_mut() {
+                None => return Ok(0),
+                Some(Section::Bytes(bytes)) => bytes.read(buf)?,
+                Some(Section::Range(range)) => {
+                    // seek to range start
+                    let remaining = match self.remaining {
+                        Some(remaining) => remaining,
+                        None => {
+                            self.source.seek(SeekFrom::Start(self.base + range.start))?;
+                            range.end - range.start
+                        }
+                    };
+
+                    // read up to remaining length
+                    let max = buf.len().min(remaining.min(usize::MAX as u64) as usize);
+                    let length = match max {
+                        0 => 0,
+                        _ => self.source.read(&mut buf[..max])?,
+                    };
+                    if length == 0 && remaining > 0 {
+                        return Err(Error::new(ErrorKind::UnexpectedEof, "Range exceeds source"));
+                    }
+                    self.remaining = Some(remaining - length as u64);
+                    length
+                }
+            };
+
+            // next section if done
+            if length == 0 {
+                self.sections.pop_front();
+                self.remaining = None;
+            } else {
+                return Ok(length);
+            }
+        }
+    }
+}
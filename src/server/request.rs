@@ -1,16 +1,91 @@
 //! HTTP request parsing
 
-use crate::server::{HttpSettings, Stream};
-use kern::byte::{split, splitn};
+use crate::common::{percent_decode, percent_decode_utf8};
+use crate::server::{
+    decode_key, is_token, parse_cookies, parse_header_value, parse_multipart, parse_parameters,
+    split_header_line, BodyReader, BodyState, ContentCoding, HeaderMap, HttpSettings,
+    MultipartPart, MultipartReader, Parameters,
+};
 use kern::Fail;
 use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter, Result as FmtResult};
 use std::io::prelude::Read;
+use std::str::FromStr;
 
-/// HTTP request method (GET or POST)
-#[derive(Debug, PartialEq)]
+/// HTTP request method
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum HttpMethod {
     GET,
+    HEAD,
     POST,
+    PUT,
+    DELETE,
+    CONNECT,
+    OPTIONS,
+    TRACE,
+    PATCH,
+    /// Extension method with raw token
+    Extension(String),
+}
+
+impl HttpMethod {
+    /// Parse method token (case-sensitive)
+    pub fn parse(token: &str) -> Result<Self, Fail> {
+        // match standard methods
+        Ok(match token {
+            "GET" => Self::GET,
+            "HEAD" => Self::HEAD,
+            "POST" => Self::POST,
+            "PUT" => Self::PUT,
+            "DELETE" => Self::DELETE,
+            "CONNECT" => Self::CONNECT,
+            "OPTIONS" => Self::OPTIONS,
+            "TRACE" => Self::TRACE,
+            "PATCH" => Self::PATCH,
+            token => {
+                // check if valid token
+                if !is_token(token) {
+                    return Fail::from("Malformed method in header");
+                }
+                Self::Extension(token.to_string())
+            }
+        })
+    }
+
+    /// Get method token
+    pub fn as_str(&self) -> &str {
+        match self {
+            Self::GET => "GET",
+            Self::HEAD => "HEAD",
+            Self::POST => "POST",
+            Self::PUT => "PUT",
+            Self::DELETE => "DELETE",
+            Self::CONNECT => "CONNECT",
+            Self::OPTIONS => "OPTIONS",
+            Self::TRACE => "TRACE",
+            Self::PATCH => "PATCH",
+            Self::Extension(token) => token,
+        }
+    }
+
+    /// Check if extension method
+    pub fn is_extension(&self) -> bool {
+        matches!(self, Self::Extension(_))
+    }
+}
+
+impl FromStr for HttpMethod {
+    type Err = Fail;
+
+    fn from_str(token: &str) -> Result<Self, Fail> {
+        Self::parse(token)
+    }
+}
+
+impl Display for HttpMethod {
+    fn fmt(&self, formatter: &mut Formatter) -> FmtResult {
+        write!(formatter, "{}", self.as_str())
+    }
 }
 
 /// HTTP request structure
@@ -18,10 +93,21 @@ pub enum HttpMethod {
 pub struct HttpRequest<'a> {
     method: HttpMethod,
     url: &'a str,
-    headers: BTreeMap<String, &'a str>,
-    get: BTreeMap<String, &'a str>,
+    version: &'a str,
+    path: String,
+    query: &'a str,
+    headers: HeaderMap,
+    cookies: Parameters<String>,
+    get_raw: Parameters<&'a str>,
+    get_params: Parameters<String>,
+    get: BTreeMap<String, String>,
+    post_params: Parameters<Vec<u8>>,
     post: BTreeMap<String, Vec<u8>>,
+    parts: Vec<MultipartPart>,
     body: Vec<u8>,
+    reader: BodyReader<'a>,
+    max_body_size: usize,
+    params: BTreeMap<String, String>,
 }
 
 impl<'a> HttpRequest<'a> {
@@ -31,54 +117,241 @@ impl<'a> HttpRequest<'a> {
         &self.method
     }
 
-    /// Get URL
+    /// Get URL (raw path without query)
     pub fn url(&self) -> &str {
         // return URL
         self.url
     }
 
-    /// Get headers map
-    pub fn headers(&self) -> &BTreeMap<String, &str> {
+    /// Get percent-decoded URL path
+    pub fn path(&self) -> &str {
+        // return decoded path
+        &self.path
+    }
+
+    /// Get raw query string
+    pub fn query(&self) -> &str {
+        // return raw query string
+        self.query
+    }
+
+    /// Get HTTP version (e.g. HTTP/1.1)
+    pub fn version(&self) -> &str {
+        // return HTTP version
+        self.version
+    }
+
+    /// Check if client wants to keep the connection alive
+    pub fn keep_alive(&self) -> bool {
+        // check connection header tokens
+        let connection = self
+            .headers
+            .get("connection")
+            .map(|connection| connection.to_lowercase())
+            .unwrap_or_default();
+        let has_token = |token| connection.split(',').any(|t| t.trim() == token);
+
+        // HTTP/1.1 defaults to keep-alive, HTTP/1.0 requires it explicitly
+        if has_token("close") {
+            false
+        } else if self.version == "HTTP/1.1" {
+            true
+        } else {
+            has_token("keep-alive")
+        }
+    }
+
+    /// Get headers map (lowercase names)
+    pub fn headers(&self) -> &HeaderMap {
         // return headers map
         &self.headers
     }
 
-    /// Get GET parameters
-    pub fn get(&self) -> &BTreeMap<String, &str> {
+    /// Get mutable headers map
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        // return mutable headers map
+        &mut self.headers
+    }
+
+    /// Get cookies
+    pub fn cookies(&self) -> &Parameters<String> {
+        // return cookies
+        &self.cookies
+    }
+
+    /// Get first cookie value with name
+    pub fn cookie(&self, name: &str) -> Option<&str> {
+        // return first cookie value
+        self.cookies.get(name).map(|value| value.as_str())
+    }
+
+    /// Get content coding of body (error if unsupported)
+    pub fn content_coding(&self) -> Result<Option<ContentCoding>, Fail> {
+        // parse content encoding header
+        parse_content_coding(self.headers.get("content-encoding"))
+    }
+
+    /// Get trailers of chunked body (available after reading body)
+    pub fn trailers(&self) -> Option<&HeaderMap> {
+        // return trailers map
+        self.reader.state().trailers()
+    }
+
+    /// Get percent-decoded GET parameters (last value of repeated keys)
+    pub fn get(&self) -> &BTreeMap<String, String> {
         // return GET parameters map
         &self.get
     }
 
-    /// Get POST parameters
+    /// Get all percent-decoded GET parameters (repeated keys preserved)
+    pub fn get_params(&self) -> &Parameters<String> {
+        // return GET parameters
+        &self.get_params
+    }
+
+    /// Get all percent-decoded GET values of key
+    pub fn get_all(&self, key: &str) -> Vec<&str> {
+        // return values of key
+        self.get_params
+            .get_all(key)
+            .into_iter()
+            .map(|v| v.as_str())
+            .collect()
+    }
+
+    /// Get raw GET parameters
+    pub fn get_raw(&self) -> &Parameters<&str> {
+        // return raw GET parameters
+        &self.get_raw
+    }
+
+    /// Get percent-decoded POST parameters of buffered body (last value of repeated keys, no multipart files)
     pub fn post(&self) -> &BTreeMap<String, Vec<u8>> {
         // return POST parameters map
         &self.post
     }
 
-    /// Get POST parameters
+    /// Get all percent-decoded POST parameters of buffered body (repeated keys preserved, no multipart files)
+    pub fn post_params(&self) -> &Parameters<Vec<u8>> {
+        // return POST parameters
+        &self.post_params
+    }
+
+    /// Get all percent-decoded POST values of key
+    pub fn post_all(&self, key: &str) -> Vec<&[u8]> {
+        // return values of key
+        self.post_params
+            .get_all(key)
+            .into_iter()
+            .map(|v| v.as_slice())
+            .collect()
+    }
+
+    /// Get percent-decoded POST parameters as UTF-8
     pub fn post_utf8(&self) -> BTreeMap<String, String> {
         // init map and iterate through byte map
         let mut post_utf8 = BTreeMap::new();
         for (k, v) in &self.post {
             // parse and insert
-            post_utf8.insert(k.to_string(), String::from_utf8_lossy(&v).to_string());
+            post_utf8.insert(k.to_string(), String::from_utf8_lossy(v).to_string());
         }
 
         // return new UTF-8 POST parameters map
         post_utf8
     }
 
-    /// Get body
+    /// Get multipart form data parts of buffered body (repeated names preserved)
+    pub fn parts(&self) -> &[MultipartPart] {
+        // return multipart parts
+        &self.parts
+    }
+
+    /// Get streaming multipart reader over body (use with unbuffered body to avoid holding it in memory)
+    ///
+    /// Data of all parts is limited to max body size (change with set_max_total_size)
+    pub fn multipart(&mut self) -> Result<MultipartReader<Box<dyn Read + '_>>, Fail> {
+        // get boundary
+        let (content_type, params) =
+            parse_header_value(self.headers.get("content-type").unwrap_or_default());
+        if !content_type.eq_ignore_ascii_case("multipart/form-data") {
+            return Fail::from("Content-Type is not multipart/form-data");
+        }
+        let boundary = params
+            .into_iter()
+            .find(|(key, _)| key == "boundary")
+            .map(|(_, boundary)| boundary)
+            .ok_or_else(|| Fail::new("Missing multipart boundary"))?;
+
+        // read buffered body or stream
+        let reader: Box<dyn Read + '_> = match self.body.is_empty() {
+            true => Box::new(&mut self.reader),
+            false => Box::new(&self.body[..]),
+        };
+        Ok(MultipartReader::new(reader, &boundary).set_max_total_size(self.max_body_size))
+    }
+
+    /// Get first multipart form data part with name
+    pub fn part(&self, name: &str) -> Option<&MultipartPart> {
+        // find part by name
+        self.parts.iter().find(|part| part.name() == name)
+    }
+
+    /// Get path parameter extracted by router
+    pub fn param(&self, name: &str) -> Option<&str> {
+        // return path parameter
+        self.params.get(name).map(|value| value.as_str())
+    }
+
+    /// Get path parameters extracted by router
+    pub fn params(&self) -> &BTreeMap<String, String> {
+        // return path parameters map
+        &self.params
+    }
+
+    /// Get mutable path parameters (e.g. to pass data from middleware)
+    pub fn params_mut(&mut self) -> &mut BTreeMap<String, String> {
+        // return mutable path parameters map
+        &mut self.params
+    }
+
+    /// Get buffered body (empty until buffered with buffer_body or read_body)
     pub fn body(&self) -> &[u8] {
         // return body string
         &self.body
     }
 
+    /// Get streaming body reader (at end if body is buffered)
+    pub fn body_reader(&mut self) -> &mut BodyReader<'a> {
+        // return body reader
+        &mut self.reader
+    }
+
+    /// Buffer remaining body with max body size of settings and parse POST parameters
+    pub fn buffer_body(&mut self) -> Result<&[u8], Fail> {
+        // read with settings limit
+        self.read_body(self.max_body_size)
+    }
+
+    /// Read remaining body into buffer with size limit and parse POST parameters
+    pub fn read_body(&mut self, max_size: usize) -> Result<&[u8], Fail> {
+        // read body
+        self.reader.read_to_limit(&mut self.body, max_size)?;
+
+        // parse POST parameters and multipart parts
+        let (post_params, parts) = parse_post(&self.headers, &self.body)?;
+        self.post_params = post_params;
+        self.post = self.post_params.to_map();
+        self.parts = parts;
+
+        // return body
+        Ok(&self.body)
+    }
+
     /// Parse HTTP request
     pub fn from(
         raw_header: &'a str,
-        mut raw_body: Vec<u8>,
-        stream: &mut Stream,
+        body_state: &'a mut BodyState,
+        stream: &'a mut (dyn Read + 'a),
         http_settings: &HttpSettings,
     ) -> Result<Self, Fail> {
         // split header
@@ -89,235 +362,185 @@ impl<'a> HttpRequest<'a> {
             .split(' ');
 
         // parse method
-        let method = if reqln
-            .next()
-            .ok_or_else(|| Fail::new("No method in header"))?
-            == "POST"
-        {
-            HttpMethod::POST
-        } else {
-            HttpMethod::GET
-        };
+        let method = HttpMethod::parse(
+            reqln
+                .next()
+                .ok_or_else(|| Fail::new("No method in header"))?,
+        )?;
 
         // parse url and split raw get parameters
-        let mut get_raw = "";
+        let mut query = "";
         let url = if let Some(full_url) = reqln.next() {
             let mut split_url = full_url.splitn(2, '?');
             let url = split_url
                 .next()
                 .ok_or_else(|| Fail::new("No URL in header"))?;
             if let Some(params) = split_url.next() {
-                get_raw = params;
+                query = params;
             }
             url
         } else {
             "/"
         };
 
+        // parse HTTP version
+        let version = reqln.next().unwrap_or("HTTP/1.0");
+
         // parse headers
-        let mut headers = BTreeMap::new();
-        header.for_each(|hl| {
-            let mut hls = hl.splitn(2, ':');
-            if let (Some(key), Some(value)) = (hls.next(), hls.next()) {
-                headers.insert(key.trim().to_lowercase(), value.trim());
-            }
-        });
+        let mut headers = HeaderMap::new();
+        for hl in header.filter(|hl| !hl.is_empty()) {
+            let (key, value) =
+                split_header_line(hl).ok_or_else(|| Fail::new("Malformed header line"))?;
+            headers
+                .append(key, value)
+                .or_else(|_| Fail::from("Malformed header line"))?;
+        }
 
-        // get content length
-        let buf_len = if let Some(buf_len) = headers.get("Content-Length") {
-            Some(buf_len)
-        } else {
-            headers.get("content-length")
+        // get content length (repeated values must match) and transfer encoding
+        let lengths = headers.get_all("content-length");
+        if lengths.iter().any(|length| *length != lengths[0]) {
+            return Fail::from("Conflicting Content-Length in header");
+        }
+        let buf_len = lengths.first().copied();
+        let chunked = match headers.get("transfer-encoding") {
+            Some(_) if buf_len.is_some() => {
+                return Fail::from("Both Transfer-Encoding and Content-Length in header")
+            }
+            Some(encoding)
+                if encoding.to_lowercase().trim() == "chunked"
+                    && headers.get_all("transfer-encoding").len() == 1 =>
+            {
+                true
+            }
+            Some(_) => return Fail::from("Unsupported Transfer-Encoding"),
+            None => false,
         };
 
-        // read rest of body
-        let mut body = Vec::new();
+        // set body framing
         if let Some(buf_len) = buf_len {
-            // parse buffer length
-            let con_len = buf_len
-                .parse::<usize>()
-                .ok()
+            // parse buffer length (digits only, no sign)
+            let con_len = Some(buf_len)
+                .filter(|buf_len| {
+                    !buf_len.is_empty() && buf_len.bytes().all(|b| b.is_ascii_digit())
+                })
+                .and_then(|buf_len| buf_len.parse::<usize>().ok())
                 .ok_or_else(|| Fail::new("Content-Length is not of type usize"))?;
+            body_state.set_length(con_len);
+        } else if chunked {
+            body_state.set_chunked(http_settings.max_header_size);
+        }
+        let mut reader = BodyReader::new(stream, body_state);
 
-            // check if body size is ok.
-            if con_len > http_settings.max_body_size {
-                return Fail::from("Max body size exceeded");
-            }
-
-            // read body
-            let mut read_fails = 0;
-            while raw_body.len() < con_len {
-                // read next buffer
-                let mut rest_body = vec![0u8; http_settings.body_buffer];
-                let length = stream
-                    .read(&mut rest_body)
-                    .ok()
-                    .ok_or_else(|| Fail::new("Stream broken"))?;
-                rest_body.truncate(length);
-                raw_body.append(&mut rest_body);
-
-                // check if didn't read fully
-                if length < http_settings.body_buffer {
-                    read_fails += 1;
-
-                    // failed too often
-                    if read_fails > http_settings.body_read_attempts {
-                        return Fail::from("Read body failed too often");
-                    }
-                }
-            }
-
-            body = raw_body;
+        // decode body content (unsupported codings are rejected before handling)
+        let coding = parse_content_coding(headers.get("content-encoding"));
+        if let Ok(Some(coding)) = coding {
+            reader.set_content_coding(coding, http_settings.max_body_size);
         }
 
+        // decode path
+        let path = percent_decode_utf8(url, false)?;
+
         // parse GET and POST parameters
-        let get = parse_parameters(get_raw, |v| v)?;
-        let post = parse_post(&headers, &body).unwrap_or_default();
+        let get_raw = parse_parameters(query, |k| Ok(k.to_string()), Ok)?;
+        let get_params = parse_parameters(query, decode_key, |v| percent_decode_utf8(v, true))?;
 
-        // return request
-        Ok(Self {
+        // create request
+        let mut request = Self {
             method,
             url,
+            version,
+            path,
+            query,
+            cookies: parse_cookies(&headers.get_all("cookie").join("; ")),
             headers,
-            get,
-            post,
-            body,
-        })
-    }
-}
+            get_raw,
+            get: get_params.to_map(),
+            get_params,
+            post: BTreeMap::new(),
+            post_params: Parameters::new(),
+            parts: Vec::new(),
+            body: Vec::new(),
+            reader,
+            max_body_size: http_settings.max_body_size,
+            params: BTreeMap::new(),
+        };
 
-/// Parse POST parameters to map
-fn parse_post(
-    headers: &BTreeMap<String, &str>,
-    body: &[u8],
-) -> Result<BTreeMap<String, Vec<u8>>, Fail> {
-    match headers.get("content-type") {
-        Some(&content_type_header) => {
-            let mut content_type_header = content_type_header.split(';').map(|s| s.trim());
-            let mut content_type = None;
-            let boundary = content_type_header.find_map(|s| {
-                if s.starts_with("boundary=") {
-                    return s.split('=').nth(1);
-                } else if content_type.is_none() {
-                    content_type = Some(s);
-                }
-                None
-            });
-            match content_type {
-                Some(content_type) => {
-                    if content_type == "multipart/form-data" {
-                        parse_post_upload(
-                            body,
-                            boundary.ok_or_else(|| Fail::new("post upload, but no boundary"))?,
-                        )
-                    } else {
-                        parse_parameters(
-                            &String::from_utf8(body.to_vec()).or_else(Fail::from)?,
-                            |v| v.as_bytes().to_vec(),
-                        )
-                    }
-                }
-                None => parse_parameters(
-                    &String::from_utf8(body.to_vec()).or_else(Fail::from)?,
-                    |v| v.as_bytes().to_vec(),
-                ),
-            }
+        // read body if buffered
+        if http_settings.buffer_body && coding.is_ok() {
+            request.read_body(http_settings.max_body_size)?;
         }
-        None => parse_parameters(
-            &String::from_utf8(body.to_vec()).or_else(Fail::from)?,
-            |v| v.as_bytes().to_vec(),
-        ),
+
+        // return request
+        Ok(request)
     }
 }
 
-/// Parse POST upload to map
-fn parse_post_upload(body: &[u8], boundary: &str) -> Result<BTreeMap<String, Vec<u8>>, Fail> {
-    // parameters map
-    let mut params = BTreeMap::new();
-
-    // split body into sections
-    let mut sections = split(&body, &format!("--{}\r\n", boundary));
-    sections.remove(0);
-    for mut section in sections {
-        // check if last section
-        let last_sep = format!("--{}--\r\n", boundary);
-        if section.ends_with(last_sep.as_bytes()) {
-            // remove ending seperator from last section
-            section = &section[..(section.len() - last_sep.len() - 2)];
-        }
-        // split lines (max 3)
-        let lines = splitn(3, &section, b"\r\n");
-
-        // parse name
-        let name = String::from_utf8_lossy(lines[0])
-            .split(';')
-            .map(|s| s.trim())
-            .find_map(|s| {
-                if s.starts_with("name=") {
-                    let name = s.split('=').nth(1)?;
-                    Some(name[1..(name.len() - 1)].to_lowercase())
-                } else {
-                    None
-                }
-            })
-            .ok_or_else(|| Fail::new("missing name in post body section"))?;
-
-        // get value
-        let data_section = lines
-            .get(2)
-            .ok_or_else(|| Fail::new("broken section in post body"))?;
-        let data_lines = splitn(2, data_section, b"\r\n");
-        let next_data_line = data_lines
-            .get(0)
-            .ok_or_else(|| Fail::new("broken section in post body"))?;
-        let value = if let Some(file_data_line) = data_lines.get(1) {
-            if next_data_line.is_empty() {
-                file_data_line.to_vec()
-            } else if file_data_line.is_empty() {
-                next_data_line.to_vec()
-            } else {
-                [&next_data_line[..], &b"\r\n"[..], &file_data_line[..]]
-                    .concat()
-                    .to_vec()
-            }
-        } else {
-            next_data_line.to_vec()
-        };
-
-        // insert into map
-        params.insert(name, value);
+/// Parse Content-Encoding header (only a single gzip or deflate coding is supported)
+fn parse_content_coding(header: Option<&str>) -> Result<Option<ContentCoding>, Fail> {
+    // ignore identity
+    let codings: Vec<&str> = header
+        .unwrap_or_default()
+        .split(',')
+        .map(|coding| coding.trim())
+        .filter(|coding| !coding.is_empty() && !coding.eq_ignore_ascii_case("identity"))
+        .collect();
+
+    // parse coding
+    match codings.as_slice() {
+        [] => Ok(None),
+        [coding] => ContentCoding::parse(coding)
+            .map(Some)
+            .ok_or_else(|| Fail::new(format!("Content-Encoding {} not supported", coding))),
+        _ => Fail::from("Multiple Content-Encodings not supported"),
     }
-
-    // return parameters map
-    Ok(params)
 }
 
-/// Parse GET parameters to map
-fn parse_parameters<'a, V>(
-    raw: &'a str,
-    process_value: fn(&'a str) -> V,
-) -> Result<BTreeMap<String, V>, Fail> {
-    // parameters map
-    let mut params = BTreeMap::new();
-
-    // split parameters by ampersand
-    for p in raw.split('&') {
-        // split key and value and add to map
-        let mut ps = p.splitn(2, '=');
-        params.insert(
-            ps.next()
-                .ok_or_else(|| Fail::new("broken x-www-form-urlencoded parameters"))?
-                .trim()
-                .to_lowercase(), // trimmed key
-            // correct value type
-            process_value(if let Some(value) = ps.next() {
-                value.trim() // trimmed value
-            } else {
-                "" // no value, is option
-            }),
-        );
-    }
-
-    // return parameters map
-    Ok(params)
+/// Parse POST parameters (and parts of multipart body, file parts only in parts)
+fn parse_post(
+    headers: &HeaderMap,
+    body: &[u8],
+) -> Result<(Parameters<Vec<u8>>, Vec<MultipartPart>), Fail> {
+    // split content type and boundary
+    let (content_type, params) =
+        parse_header_value(headers.get("content-type").unwrap_or_default());
+    let boundary = params
+        .into_iter()
+        .find(|(key, _)| key == "boundary")
+        .map(|(_, boundary)| boundary);
+
+    // parse depending on content type
+    match content_type.to_lowercase().as_str() {
+        "multipart/form-data" => {
+            // broken multipart bodies are an error (empty body has no parts)
+            let parts = match boundary {
+                _ if body.is_empty() => Vec::new(),
+                Some(boundary) => parse_multipart(body, &boundary)?,
+                None => return Fail::from("Missing multipart boundary"),
+            };
+
+            // only non-file fields as parameters (file data stays in parts)
+            let mut params = Parameters::new();
+            for part in parts.iter().filter(|part| !part.is_file()) {
+                params.add(part.name().to_string(), part.data().to_vec());
+            }
+            Ok((params, parts))
+        }
+        "application/x-www-form-urlencoded" => match std::str::from_utf8(body) {
+            Ok(body) => Ok((
+                parse_parameters(body, decode_key, |v| percent_decode(v, true))?,
+                Vec::new(),
+            )),
+            Err(_) => Ok((Parameters::new(), Vec::new())),
+        },
+        // try to parse unknown content types as parameters
+        _ => Ok((
+            String::from_utf8(body.to_vec())
+                .ok()
+                .and_then(|body| {
+                    parse_parameters(&body, decode_key, |v| percent_decode(v, true)).ok()
+                })
+                .unwrap_or_default(),
+            Vec::new(),
+        )),
+    }
 }
@@ -1,13 +1,19 @@
 //! HTTP response
 
-use std::collections::BTreeMap;
-use std::convert::AsRef;
+use crate::server::{ChunkedDecoder, ChunkedWriter, Cookie, HeaderMap, StatusCode};
+use kern::byte::scan;
+use kern::Fail;
+use std::convert::{AsRef, TryFrom};
+use std::fmt;
+use std::io::prelude::{Read, Seek, Write};
+use std::io::{copy, BufWriter, Result as IoResult};
 
 /// Additional response data
 #[derive(Clone, Default, Debug)]
 pub struct ResponseData<'a> {
     pub status: &'a str,
-    pub headers: BTreeMap<&'a str, &'a str>,
+    pub headers: HeaderMap,
+    pub charset: Option<&'a str>,
 }
 
 impl<'a> ResponseData<'a> {
@@ -15,7 +21,8 @@ impl<'a> ResponseData<'a> {
     pub fn new() -> Self {
         Self {
             status: "200 OK",
-            headers: BTreeMap::new(),
+            headers: HeaderMap::new(),
+            charset: None,
         }
     }
 
@@ -24,9 +31,434 @@ impl<'a> ResponseData<'a> {
         self.status = status;
         self
     }
+
+    /// Add header (invalid names or values are ignored)
+    pub fn add_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.append(name, value).ok();
+        self
+    }
+
+    /// Add charset to text content types (e.g. utf-8)
+    pub fn set_charset(mut self, charset: &'a str) -> Self {
+        self.charset = Some(charset);
+        self
+    }
+}
+
+/// Seekable streaming body source
+pub trait ReadSeek: Read + Seek + Send {}
+
+impl<T: Read + Seek + Send> ReadSeek for T {}
+
+/// Function writing a streaming response body
+pub type WriteBody = Box<dyn FnOnce(&mut BodyWriter) -> Result<(), Fail> + Send>;
+
+/// Response body
+pub enum ResponseBody {
+    /// In-memory body
+    Bytes(Vec<u8>),
+    /// Streaming body with length if known (chunked otherwise)
+    Reader(Box<dyn Read + Send>, Option<u64>),
+    /// Seekable streaming body with length from current position (supports range requests)
+    Seekable(Box<dyn ReadSeek>, u64),
+    /// Body written while sending (chunked, may add trailers)
+    Writer(WriteBody),
+}
+
+impl ResponseBody {
+    /// Get in-memory body (None if streaming)
+    pub fn bytes(&self) -> Option<&[u8]> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    /// Get length if known
+    pub fn len(&self) -> Option<u64> {
+        match self {
+            Self::Bytes(bytes) => Some(bytes.len() as u64),
+            Self::Reader(_, length) => *length,
+            Self::Seekable(_, length) => Some(*length),
+            Self::Writer(_) => None,
+        }
+    }
+
+    /// Check if body is known to be empty
+    pub fn is_empty(&self) -> bool {
+        self.len() == Some(0)
+    }
+}
+
+impl Default for ResponseBody {
+    fn default() -> Self {
+        Self::Bytes(Vec::new())
+    }
+}
+
+impl fmt::Debug for ResponseBody {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::Bytes(bytes) => f.debug_tuple("Bytes").field(bytes).finish(),
+            Self::Reader(_, length) => f.debug_tuple("Reader").field(length).finish(),
+            Self::Seekable(_, length) => f.debug_tuple("Seekable").field(length).finish(),
+            Self::Writer(_) => f.write_str("Writer"),
+        }
+    }
+}
+
+/// Buffered streaming response body writer (chunked or close-delimited for HTTP/1.0)
+pub struct BodyWriter<'a> {
+    writer: BodyWriterInner<'a>,
+    trailers: HeaderMap,
+}
+
+/// Body writer framing
+enum BodyWriterInner<'a> {
+    Chunked(BufWriter<ChunkedWriter<&'a mut dyn Write>>),
+    Plain(&'a mut dyn Write),
+}
+
+impl<'a> BodyWriter<'a> {
+    /// Create new body writer
+    pub(crate) fn new(writer: &'a mut dyn Write, chunked: bool) -> Self {
+        Self {
+            writer: match chunked {
+                true => BodyWriterInner::Chunked(BufWriter::new(ChunkedWriter::new(writer))),
+                false => BodyWriterInner::Plain(writer),
+            },
+            trailers: HeaderMap::new(),
+        }
+    }
+
+    /// Check if body is chunked (trailers are discarded otherwise)
+    pub fn is_chunked(&self) -> bool {
+        matches!(self.writer, BodyWriterInner::Chunked(_))
+    }
+
+    /// Add trailer sent after body (announce with trailer header, invalid names or values are ignored)
+    pub fn add_trailer(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.trailers.append(name, value).ok();
+    }
+
+    /// Take added trailers
+    pub(crate) fn take_trailers(&mut self) -> HeaderMap {
+        std::mem::take(&mut self.trailers)
+    }
+
+    /// Write last chunk and trailers
+    pub(crate) fn finish(self) -> IoResult<()> {
+        match self.writer {
+            BodyWriterInner::Chunked(writer) => writer
+                .into_inner()
+                .map_err(|err| err.into_error())?
+                .finish(&self.trailers)
+                .map(|_| ()),
+            BodyWriterInner::Plain(writer) => writer.flush(),
+        }
+    }
+}
+
+impl Write for BodyWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> IoResult<usize> {
+        match &mut self.writer {
+            BodyWriterInner::Chunked(writer) => writer.write(buf),
+            BodyWriterInner::Plain(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> IoResult<()> {
+        match &mut self.writer {
+            BodyWriterInner::Chunked(writer) => writer.flush(),
+            BodyWriterInner::Plain(writer) => writer.flush(),
+        }
+    }
+}
+
+impl fmt::Debug for BodyWriter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BodyWriter")
+            .field("chunked", &self.is_chunked())
+            .field("trailers", &self.trailers)
+            .finish()
+    }
+}
+
+/// HTTP response builder
+#[derive(Debug, Default)]
+pub struct Response {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: ResponseBody,
+}
+
+impl Response {
+    /// Create new empty 200 OK response
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Change status
+    pub fn set_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    /// Add header (keeps existing values, invalid names or values are ignored)
+    pub fn add_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.append(name, value).ok();
+        self
+    }
+
+    /// Set header (replaces existing values, invalid names or values are ignored)
+    pub fn set_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name, value).ok();
+        self
+    }
+
+    /// Add Set-Cookie header (keeps other cookies)
+    pub fn add_cookie(self, cookie: &Cookie) -> Self {
+        self.add_header("set-cookie", cookie.to_header_value())
+    }
+
+    /// Set content type
+    pub fn set_content_type(self, content_type: impl Into<String>) -> Self {
+        self.set_header("content-type", content_type)
+    }
+
+    /// Set body
+    pub fn set_body(mut self, body: impl Into<Vec<u8>>) -> Self {
+        self.body = ResponseBody::Bytes(body.into());
+        self
+    }
+
+    /// Set streaming body (chunked if length unknown)
+    pub fn set_body_reader(
+        mut self,
+        reader: impl Read + Send + 'static,
+        length: Option<u64>,
+    ) -> Self {
+        self.body = ResponseBody::Reader(Box::new(reader), length);
+        self
+    }
+
+    /// Set seekable streaming body read from current position (supports range requests)
+    pub fn set_body_seekable(mut self, reader: impl ReadSeek + 'static, length: u64) -> Self {
+        self.body = ResponseBody::Seekable(Box::new(reader), length);
+        self
+    }
+
+    /// Set body of any kind
+    pub fn set_response_body(mut self, body: ResponseBody) -> Self {
+        self.body = body;
+        self
+    }
+
+    /// Set body written while sending (chunked, trailers can be added to the writer)
+    pub fn set_body_writer(
+        mut self,
+        write_body: impl FnOnce(&mut BodyWriter) -> Result<(), Fail> + Send + 'static,
+    ) -> Self {
+        self.body = ResponseBody::Writer(Box::new(write_body));
+        self
+    }
+
+    /// Get status
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+
+    /// Get headers
+    pub fn headers(&self) -> &HeaderMap {
+        &self.headers
+    }
+
+    /// Get mutable headers (checked insertion)
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+
+    /// Get first value of header
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.get(name)
+    }
+
+    /// Get body
+    pub fn body(&self) -> &ResponseBody {
+        &self.body
+    }
+
+    /// Take body and leave empty body
+    pub fn take_body(&mut self) -> ResponseBody {
+        std::mem::take(&mut self.body)
+    }
+
+    /// Parse serialized response (framing is recalculated, chunked bodies are decoded)
+    pub fn parse(raw: &[u8]) -> Result<Self, Fail> {
+        // split header and body
+        let end = scan(raw, b"\r\n\r\n").ok_or_else(|| Fail::new("Response header incomplete"))?;
+        let header = std::str::from_utf8(&raw[..end]).or_else(Fail::from)?;
+        let mut lines = header.split("\r\n");
+
+        // parse status line
+        let status_line = lines.next().unwrap_or_default();
+        if !status_line.starts_with("HTTP/") {
+            return Fail::from("Response status line invalid");
+        }
+        let status = StatusCode::parse(
+            status_line
+                .split_once(' ')
+                .map(|(_, status)| status)
+                .unwrap_or_default(),
+        )?;
+
+        // parse headers without generated ones
+        let mut response = Self::new().set_status(status);
+        let mut chunked = false;
+        for line in lines {
+            let mut ls = line.splitn(2, ':');
+            let (name, value) = match (ls.next(), ls.next()) {
+                (Some(name), Some(value)) => (name.trim(), value.trim()),
+                _ => return Fail::from("Response header line invalid"),
+            };
+            if name.eq_ignore_ascii_case("transfer-encoding") {
+                match value.eq_ignore_ascii_case("chunked") && !chunked {
+                    true => chunked = true,
+                    false => return Fail::from("Unsupported response transfer-encoding"),
+                }
+            } else if !is_framing_header(name) && !name.eq_ignore_ascii_case("server") {
+                response = response.add_header(name, value);
+            }
+        }
+
+        // set body (decode chunked body without trailers)
+        let body = &raw[(end + 4)..];
+        match chunked {
+            true => {
+                let mut decoder = ChunkedDecoder::new();
+                let mut decoded = Vec::new();
+                decoder.decode(body, &mut decoded)?;
+                if !decoder.is_done() {
+                    return Fail::from("Chunked response body incomplete");
+                }
+                Ok(response.set_body(decoded))
+            }
+            false => Ok(response.set_body(body)),
+        }
+    }
+
+    /// Check if status allows body (not 1xx, 204 or 304)
+    fn allows_body(&self) -> bool {
+        !(self.status.is_informational()
+            || self.status == StatusCode::NO_CONTENT
+            || self.status == StatusCode::NOT_MODIFIED)
+    }
+
+    /// Write response to writer (streaming bodies are not buffered)
+    pub fn write_to(self, writer: &mut impl Write) -> Result<(), Fail> {
+        self.write(writer, true, true)
+    }
+
+    /// Write response with or without body (e.g. for HEAD requests)
+    ///
+    /// Bodies of unknown length are close-delimited if not chunked (e.g. for HTTP/1.0)
+    pub(crate) fn write(
+        self,
+        writer: &mut impl Write,
+        with_body: bool,
+        chunked: bool,
+    ) -> Result<(), Fail> {
+        // status line and headers (framing headers are generated)
+        let mut header = format!("HTTP/1.1 {}\r\nserver: ltheinrich.de/lhi", self.status);
+        self.headers
+            .iter()
+            .filter(|(k, _)| !is_framing_header(k))
+            .for_each(|(k, v)| {
+                header.push_str("\r\n");
+                header.push_str(k);
+                header.push_str(": ");
+                header.push_str(v);
+            });
+
+        // framing headers (1xx, 204 and 304 have no body)
+        let allows_body = self.allows_body();
+        let length = self.body.len();
+        match length {
+            _ if !allows_body => {}
+            Some(length) => header.push_str(&format!("\r\ncontent-length: {}", length)),
+            None if chunked => header.push_str("\r\ntransfer-encoding: chunked"),
+            None => {}
+        }
+        header.push_str("\r\n\r\n");
+
+        // write header
+        let mut writer = BufWriter::new(writer);
+        writer.write_all(header.as_bytes()).or_else(Fail::from)?;
+        if !allows_body || !with_body {
+            return writer.flush().or_else(Fail::from);
+        }
+
+        // write body
+        match self.body {
+            ResponseBody::Bytes(bytes) => writer.write_all(&bytes).or_else(Fail::from)?,
+            ResponseBody::Reader(mut reader, Some(length)) => {
+                copy_length(&mut reader, &mut writer, length)?
+            }
+            ResponseBody::Seekable(mut reader, length) => {
+                copy_length(&mut reader, &mut writer, length)?
+            }
+            ResponseBody::Reader(mut reader, None) => {
+                let mut body_writer = BodyWriter::new(&mut writer, chunked);
+                copy(&mut reader, &mut body_writer).or_else(Fail::from)?;
+                body_writer.finish().or_else(Fail::from)?;
+            }
+            ResponseBody::Writer(write_body) => {
+                let mut body_writer = BodyWriter::new(&mut writer, chunked);
+                write_body(&mut body_writer)?;
+                body_writer.finish().or_else(Fail::from)?;
+            }
+        }
+        writer.flush().or_else(Fail::from)
+    }
+
+    /// Serialize response (streaming bodies are read into memory)
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut response = Vec::new();
+        self.write_to(&mut response).ok();
+        response
+    }
 }
 
-/// Create HTTP response
+/// Check if header is content-length or transfer-encoding
+fn is_framing_header(name: &str) -> bool {
+    name.eq_ignore_ascii_case("content-length") || name.eq_ignore_ascii_case("transfer-encoding")
+}
+
+/// Copy exactly length bytes
+fn copy_length(reader: &mut impl Read, writer: &mut impl Write, length: u64) -> Result<(), Fail> {
+    let written = copy(&mut reader.take(length), writer).or_else(Fail::from)?;
+    match written == length {
+        true => Ok(()),
+        false => Fail::from("Response body shorter than content-length"),
+    }
+}
+
+impl From<Response> for Vec<u8> {
+    fn from(response: Response) -> Self {
+        response.into_bytes()
+    }
+}
+
+impl TryFrom<Vec<u8>> for Response {
+    type Error = Fail;
+
+    /// Parse serialized response
+    fn try_from(raw: Vec<u8>) -> Result<Self, Fail> {
+        Self::parse(&raw)
+    }
+}
+
+/// Create HTTP response (content type is omitted if empty, invalid status is sent as 500)
 pub fn respond(
     content: impl AsRef<[u8]>,
     content_type: impl AsRef<str>,
@@ -40,42 +472,44 @@ pub fn respond(
         Some(data) => data,
         None => ResponseData::new(),
     };
-    let status = data.status;
-    let mut headers = String::new();
-    data.headers.iter().for_each(|(k, v)| {
-        headers.push_str("\r\n");
-        headers.push_str(k);
-        headers.push_str(": ");
-        headers.push_str(v);
-    });
-
-    // create response
-    let mut response = Vec::new();
-    let header = format!(
-        "HTTP/1.1 {}\r\nserver: ltheinrich.de/lhi\r\ncontent-type: {}; charset=utf-8{}",
-        status,
-        content_type.as_ref(),
-        headers
-    );
-    response.extend_from_slice(header.as_bytes());
-
-    // write content
-    response.append(&mut set_content_length(content.len()));
-    response.extend_from_slice(content);
-    response.extend_from_slice(b"\r\n");
-
-    // return
-    response
-}
-
-/// create content-length header bytes
-fn set_content_length(content_length: usize) -> Vec<u8> {
-    let mut header = Vec::new();
-    header.extend_from_slice(b"\r\n");
-    header.extend_from_slice(b"content-length: ");
-    header.extend_from_slice((content_length + 2).to_string().as_bytes());
-    header.extend_from_slice(b"\r\n\r\n");
-    header
+    let status = StatusCode::parse(data.status).unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+
+    // create response with content type
+    let mut response = Response::new().set_status(status);
+    let content_type = content_type.as_ref();
+    match data.charset {
+        _ if content_type.is_empty() => {}
+        Some(charset) if is_text_type(content_type) && !content_type.contains("charset=") => {
+            response = response.set_content_type(format!("{}; charset={}", content_type, charset))
+        }
+        _ => response = response.set_content_type(content_type),
+    }
+    for (k, v) in data.headers {
+        response = response.add_header(k, v);
+    }
+
+    // write content and return
+    response.set_body(content).into_bytes()
+}
+
+/// Check if content type is textual
+pub(crate) fn is_text_type(content_type: &str) -> bool {
+    // get media type without parameters
+    let media_type = content_type
+        .split(';')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_lowercase();
+
+    // text types
+    media_type.starts_with("text/")
+        || media_type.ends_with("+xml")
+        || media_type.ends_with("+json")
+        || matches!(
+            media_type.as_str(),
+            "application/json" | "application/javascript" | "application/xml"
+        )
 }
 
 /// Create HTTP redirect response
@@ -83,20 +517,11 @@ pub fn redirect(url: impl AsRef<str>) -> Vec<u8> {
     // as ref
     let url = url.as_ref();
 
-    // set location
-    let mut headers = BTreeMap::new();
-    headers.insert("location", url);
-
-    // create response data
-    let data = ResponseData {
-        status: "303 See Other",
-        headers,
-    };
-
     // create and return response
-    respond(
-        format!("<html><head><title>Moved</title></head><body><h1>Moved</h1><p><a href=\"{0}\">{0}</a></p></body></html>", url),
-        "text/html",
-        Some(data)
-        )
+    Response::new()
+        .set_status(StatusCode::SEE_OTHER)
+        .set_header("location", url)
+        .set_content_type("text/html; charset=utf-8")
+        .set_body(format!("<html><head><title>Moved</title></head><body><h1>Moved</h1><p><a href=\"{0}\">{0}</a></p></body></html>", url))
+        .into_bytes()
 }
@@ -0,0 +1,207 @@
+//! Request router
+
+use crate::common::percent_decode_utf8;
+use crate::server::{error_response, Handler, HttpMethod, HttpRequest, Response, StatusCode};
+use kern::Fail;
+use std::collections::BTreeMap;
+
+/// Route path segment
+enum Segment {
+    Static(String),
+    Param(String),
+    Wildcard(String),
+}
+
+/// Route with method, path pattern and handler
+struct Route {
+    method: Option<HttpMethod>,
+    segments: Vec<Segment>,
+    handler: Box<dyn Handler>,
+}
+
+impl Route {
+    /// Match raw URL path and extract parameters
+    fn matches(&self, url: &str) -> Option<BTreeMap<String, String>> {
+        // split raw path
+        let mut parts = url.trim_start_matches('/').split('/');
+        let mut params = BTreeMap::new();
+
+        // compare segments
+        for segment in &self.segments {
+            match segment {
+                Segment::Static(name) => {
+                    if percent_decode_utf8(parts.next()?, false).ok()? != *name {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    let value = percent_decode_utf8(parts.next()?, false).ok()?;
+                    if value.is_empty() {
+                        return None;
+                    }
+                    params.insert(name.to_string(), value);
+                }
+                Segment::Wildcard(name) => {
+                    // capture rest of path
+                    let rest = parts
+                        .by_ref()
+                        .map(|part| percent_decode_utf8(part, false))
+                        .collect::<Result<Vec<_>, _>>()
+                        .ok()?;
+                    params.insert(name.to_string(), rest.join("/"));
+                }
+            }
+        }
+
+        // all parts must be matched
+        if parts.next().is_some() {
+            return None;
+        }
+        Some(params)
+    }
+}
+
+/// Router dispatching requests by method and path
+///
+/// Paths may contain named parameters (`/users/:id`) and a trailing wildcard (`/static/*path`)
+#[derive(Default)]
+pub struct Router {
+    routes: Vec<Route>,
+}
+
+impl Router {
+    /// Create empty router
+    pub fn new() -> Self {
+        Self { routes: Vec::new() }
+    }
+
+    /// Add route like "GET /users/:id" (without method matches any method)
+    pub fn route(self, route: &str, handler: impl Handler) -> Result<Self, Fail> {
+        // split method and path
+        let mut split = route.trim().splitn(2, ' ');
+        let first = split.next().unwrap_or_default();
+        match split.next() {
+            Some(path) => self.add(Some(HttpMethod::parse(first)?), path.trim(), handler),
+            None => self.add(None, first, handler),
+        }
+    }
+
+    /// Add GET route
+    pub fn get(self, path: &str, handler: impl Handler) -> Result<Self, Fail> {
+        self.add(Some(HttpMethod::GET), path, handler)
+    }
+
+    /// Add POST route
+    pub fn post(self, path: &str, handler: impl Handler) -> Result<Self, Fail> {
+        self.add(Some(HttpMethod::POST), path, handler)
+    }
+
+    /// Add PUT route
+    pub fn put(self, path: &str, handler: impl Handler) -> Result<Self, Fail> {
+        self.add(Some(HttpMethod::PUT), path, handler)
+    }
+
+    /// Add DELETE route
+    pub fn delete(self, path: &str, handler: impl Handler) -> Result<Self, Fail> {
+        self.add(Some(HttpMethod::DELETE), path, handler)
+    }
+
+    /// Add PATCH route
+    pub fn patch(self, path: &str, handler: impl Handler) -> Result<Self, Fail> {
+        self.add(Some(HttpMethod::PATCH), path, handler)
+    }
+
+    /// Add route for any method
+    pub fn any(self, path: &str, handler: impl Handler) -> Result<Self, Fail> {
+        self.add(None, path, handler)
+    }
+
+    /// Add route for method (None matches any method, wildcard must be the last segment)
+    pub fn add(
+        mut self,
+        method: Option<HttpMethod>,
+        path: &str,
+        handler: impl Handler,
+    ) -> Result<Self, Fail> {
+        // parse path segments
+        let segments: Vec<Segment> = path
+            .trim_start_matches('/')
+            .split('/')
+            .map(|segment| {
+                if let Some(name) = segment.strip_prefix(':') {
+                    Segment::Param(name.to_string())
+                } else if let Some(name) = segment.strip_prefix('*') {
+                    Segment::Wildcard(name.to_string())
+                } else {
+                    Segment::Static(segment.to_string())
+                }
+            })
+            .collect();
+
+        // wildcard captures rest of path
+        if let Some(pos) = segments
+            .iter()
+            .position(|segment| matches!(segment, Segment::Wildcard(_)))
+        {
+            if pos + 1 != segments.len() {
+                return Fail::from(format!("Wildcard must be the last segment in {}", path));
+            }
+        }
+
+        // add route
+        self.routes.push(Route {
+            method,
+            segments,
+            handler: Box::new(handler),
+        });
+        Ok(self)
+    }
+}
+
+impl Handler for Router {
+    fn handle(&self, req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+        let mut req = req?;
+
+        // find matching routes
+        let mut allowed = Vec::new();
+        for route in &self.routes {
+            if let Some(params) = route.matches(req.url()) {
+                match &route.method {
+                    // HEAD is handled by GET routes
+                    Some(method)
+                        if method != req.method()
+                            && !(*method == HttpMethod::GET
+                                && *req.method() == HttpMethod::HEAD) =>
+                    {
+                        allowed.push(method.as_str());
+                    }
+                    _ => {
+                        req.params_mut().extend(params);
+                        return route.handler.handle(Ok(req));
+                    }
+                }
+            }
+        }
+
+        // path not found
+        if allowed.is_empty() {
+            return Ok(error_response(
+                format!("{} not found", req.path()),
+                StatusCode::NOT_FOUND,
+            ));
+        }
+
+        // method not allowed for path
+        if allowed.contains(&"GET") {
+            allowed.push("HEAD");
+        }
+        allowed.sort_unstable();
+        allowed.dedup();
+        let allow = allowed.join(", ");
+        Ok(error_response(
+            format!("Method {} not allowed", req.method()),
+            StatusCode::METHOD_NOT_ALLOWED,
+        )
+        .set_header("allow", allow))
+    }
+}
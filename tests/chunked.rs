@@ -0,0 +1,86 @@
+use lhi::server::ChunkedDecoder;
+
+/// Decode input in pieces of step size, returns output or error message
+fn decode(mut decoder: ChunkedDecoder, input: &[u8], step: usize) -> Result<Vec<u8>, String> {
+    let mut output = Vec::new();
+    for piece in input.chunks(step) {
+        // piece must be consumed completely until done
+        let consumed = decoder
+            .decode(piece, &mut output)
+            .map_err(|err| err.to_string())?;
+        assert!(consumed == piece.len() || decoder.is_done());
+    }
+    assert!(decoder.is_done());
+    Ok(output)
+}
+
+#[test]
+fn chunked_split_reads() {
+    // decode byte by byte and in one piece
+    let input = b"5\r\nhello\r\n7\r\n, world\r\n0\r\nx-checksum: 1234\r\n\r\n";
+    for step in [1, 2, 3, input.len()] {
+        assert_eq!(
+            decode(ChunkedDecoder::new(), input, step).unwrap(),
+            b"hello, world"
+        );
+    }
+}
+
+#[test]
+fn chunked_extensions_ignored() {
+    // chunk extensions after size
+    let input = b"5;name=value\r\nhello\r\n1 ; a=\"b;c\"\r\n!\r\n0;last\r\n\r\n";
+    assert_eq!(decode(ChunkedDecoder::new(), input, 4).unwrap(), b"hello!");
+}
+
+#[test]
+fn chunked_trailers() {
+    // decode trailers case-insensitively
+    let mut decoder = ChunkedDecoder::new();
+    let mut output = Vec::new();
+    decoder
+        .decode(b"1\r\na\r\n0\r\nX-A: 1\r\nx-a: 2\r\n\r\n", &mut output)
+        .unwrap();
+    assert!(decoder.is_done());
+    assert_eq!(decoder.trailers().get_all("X-A"), vec!["1", "2"]);
+}
+
+#[test]
+fn chunked_trailer_size_limit() {
+    // many small trailer lines exceed limit
+    let mut input = b"1\r\na\r\n0\r\n".to_vec();
+    for i in 0..1000 {
+        input.extend_from_slice(format!("x-{}: 1\r\n", i).as_bytes());
+    }
+    input.extend_from_slice(b"\r\n");
+    let decoder = ChunkedDecoder::new().set_max_trailer_size(1024);
+    assert_eq!(
+        decode(decoder, &input, 100).unwrap_err(),
+        "Max trailer size exceeded"
+    );
+
+    // trailers within limit
+    let decoder = ChunkedDecoder::new().set_max_trailer_size(1024);
+    assert_eq!(
+        decode(decoder, b"1\r\na\r\n0\r\nx-a: 1\r\n\r\n", 100).unwrap(),
+        b"a"
+    );
+}
+
+#[test]
+fn chunked_size_hex_digits_only() {
+    // signs, prefixes and empty sizes are rejected
+    for size in ["+5", "-5", "0x5", " ", ""] {
+        let input = format!("{}\r\nhello\r\n0\r\n\r\n", size);
+        assert_eq!(
+            decode(ChunkedDecoder::new(), input.as_bytes(), 100).unwrap_err(),
+            "Invalid chunk size",
+            "{:?}",
+            size
+        );
+    }
+
+    // upper and lower case hex digits
+    let input = b"A\r\n0123456789\r\nb\r\nabcdefghijk\r\n0\r\n\r\n";
+    assert_eq!(decode(ChunkedDecoder::new(), input, 100).unwrap().len(), 21);
+}
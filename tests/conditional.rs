@@ -0,0 +1,214 @@
+use lhi::common::http_date;
+use lhi::server::{Conditions, HttpMethod, Response, StatusCode};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// ETag of tested resource
+const ETAG: &str = "\"abc\"";
+
+/// Modification time of tested resource
+fn modified() -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(1_600_000_000)
+}
+
+/// HTTP date relative to modification time
+fn date(offset: i64) -> Option<String> {
+    let secs = (1_600_000_000 + offset) as u64;
+    Some(http_date(UNIX_EPOCH + Duration::from_secs(secs)))
+}
+
+/// Evaluate conditions against current validators
+fn evaluate(method: HttpMethod, conditions: Conditions) -> Option<StatusCode> {
+    conditions.evaluate(&method, Some(ETAG), Some(modified()))
+}
+
+#[test]
+fn if_none_match() {
+    // strong, weak, listed and wildcard matches
+    for if_none_match in ["\"abc\"", "W/\"abc\"", "\"x\", \"abc\"", "*"] {
+        let conditions = Conditions {
+            if_none_match: Some(if_none_match.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(HttpMethod::GET, conditions.clone()),
+            Some(StatusCode::NOT_MODIFIED)
+        );
+        assert_eq!(
+            evaluate(HttpMethod::HEAD, conditions.clone()),
+            Some(StatusCode::NOT_MODIFIED)
+        );
+        assert_eq!(
+            evaluate(HttpMethod::PUT, conditions),
+            Some(StatusCode::PRECONDITION_FAILED)
+        );
+    }
+
+    // no match
+    let conditions = Conditions {
+        if_none_match: Some("\"x\", W/\"y\"".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(evaluate(HttpMethod::GET, conditions.clone()), None);
+    assert_eq!(evaluate(HttpMethod::PUT, conditions), None);
+}
+
+#[test]
+fn if_match() {
+    // strong and wildcard matches proceed
+    for if_match in ["\"abc\"", "\"x\", \"abc\"", "*"] {
+        let conditions = Conditions {
+            if_match: Some(if_match.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(evaluate(HttpMethod::PUT, conditions), None);
+    }
+
+    // weak comparison and mismatches fail
+    for if_match in ["W/\"abc\"", "\"x\"", "abc"] {
+        let conditions = Conditions {
+            if_match: Some(if_match.to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            evaluate(HttpMethod::PUT, conditions.clone()),
+            Some(StatusCode::PRECONDITION_FAILED)
+        );
+        assert_eq!(
+            evaluate(HttpMethod::GET, conditions),
+            Some(StatusCode::PRECONDITION_FAILED)
+        );
+    }
+
+    // no current representation
+    let conditions = Conditions {
+        if_match: Some("\"abc\"".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        conditions.evaluate(&HttpMethod::PUT, None, None),
+        Some(StatusCode::PRECONDITION_FAILED)
+    );
+}
+
+#[test]
+fn if_modified_since() {
+    // not modified at or after date
+    for (offset, status) in [
+        (0, Some(StatusCode::NOT_MODIFIED)),
+        (10, Some(StatusCode::NOT_MODIFIED)),
+        (-10, None),
+    ] {
+        let conditions = Conditions {
+            if_modified_since: date(offset),
+            ..Default::default()
+        };
+        assert_eq!(evaluate(HttpMethod::GET, conditions), status, "{}", offset);
+    }
+
+    // ignored for unsafe methods and invalid dates
+    let conditions = Conditions {
+        if_modified_since: date(10),
+        ..Default::default()
+    };
+    assert_eq!(evaluate(HttpMethod::POST, conditions), None);
+    let conditions = Conditions {
+        if_modified_since: Some("yesterday".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(evaluate(HttpMethod::GET, conditions), None);
+}
+
+#[test]
+fn if_unmodified_since() {
+    // fails if modified after date
+    for (offset, status) in [
+        (0, None),
+        (10, None),
+        (-10, Some(StatusCode::PRECONDITION_FAILED)),
+    ] {
+        let conditions = Conditions {
+            if_unmodified_since: date(offset),
+            ..Default::default()
+        };
+        assert_eq!(evaluate(HttpMethod::PUT, conditions.clone()), status);
+        assert_eq!(evaluate(HttpMethod::GET, conditions), status);
+    }
+
+    // invalid dates are ignored
+    let conditions = Conditions {
+        if_unmodified_since: Some("yesterday".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(evaluate(HttpMethod::PUT, conditions), None);
+}
+
+#[test]
+fn etag_conditions_take_precedence() {
+    // If-Match overrides failing If-Unmodified-Since
+    let conditions = Conditions {
+        if_match: Some(ETAG.to_string()),
+        if_unmodified_since: date(-10),
+        ..Default::default()
+    };
+    assert_eq!(evaluate(HttpMethod::PUT, conditions), None);
+
+    // If-None-Match overrides matching If-Modified-Since
+    let conditions = Conditions {
+        if_none_match: Some("\"x\"".to_string()),
+        if_modified_since: date(10),
+        ..Default::default()
+    };
+    assert_eq!(evaluate(HttpMethod::GET, conditions), None);
+
+    // failed If-Match before matching If-None-Match
+    let conditions = Conditions {
+        if_match: Some("\"x\"".to_string()),
+        if_none_match: Some(ETAG.to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        evaluate(HttpMethod::GET, conditions),
+        Some(StatusCode::PRECONDITION_FAILED)
+    );
+}
+
+#[test]
+fn apply_to_response() {
+    // 304 keeps validators only
+    let response = || {
+        Response::new()
+            .set_content_type("text/plain")
+            .set_header("etag", ETAG)
+            .set_header("last-modified", http_date(modified()))
+            .set_header("cache-control", "no-cache")
+            .set_body("content")
+    };
+    let conditions = Conditions {
+        if_none_match: Some(ETAG.to_string()),
+        ..Default::default()
+    };
+    let not_modified = conditions.apply(&HttpMethod::GET, response());
+    assert_eq!(not_modified.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(not_modified.header("etag"), Some(ETAG));
+    assert_eq!(not_modified.header("cache-control"), Some("no-cache"));
+    assert!(not_modified.header("last-modified").is_some());
+    assert!(not_modified.header("content-type").is_none());
+    assert_eq!(not_modified.body().len(), Some(0));
+
+    // 412 from Last-Modified header
+    let conditions = Conditions {
+        if_unmodified_since: date(-10),
+        ..Default::default()
+    };
+    let failed = conditions.apply(&HttpMethod::PUT, response());
+    assert_eq!(failed.status(), StatusCode::PRECONDITION_FAILED);
+
+    // unsuccessful responses are kept
+    let conditions = Conditions {
+        if_none_match: Some("*".to_string()),
+        ..Default::default()
+    };
+    let not_found = response().set_status(StatusCode::NOT_FOUND);
+    let not_found = conditions.apply(&HttpMethod::GET, not_found);
+    assert_eq!(not_found.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,174 @@
+use kern::Fail;
+use lhi::server::{handle_connection, HttpRequest, HttpSettings, Response};
+use std::io::prelude::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+/// Handler responding with request path (body is never read)
+fn path(req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+    let req = req?;
+    Ok(Response::new().set_body(req.path().to_string()))
+}
+
+/// Serve one connection with settings on random port and connect to it
+fn connect(http_settings: HttpSettings) -> (TcpStream, JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &http_settings, None, &path).unwrap();
+    });
+    (TcpStream::connect(addr).unwrap(), server)
+}
+
+/// Read one response with content-length body, returns header and body
+fn read_response(stream: &mut TcpStream) -> (String, String) {
+    // read header byte by byte
+    let mut header = Vec::new();
+    let mut byte = [0u8];
+    while !header.ends_with(b"\r\n\r\n") {
+        assert_eq!(stream.read(&mut byte).unwrap(), 1, "connection closed");
+        header.push(byte[0]);
+    }
+    let header = String::from_utf8(header).unwrap();
+
+    // read body of content-length
+    let length = header
+        .lines()
+        .find_map(|line| line.strip_prefix("content-length: "))
+        .map(|length| length.parse().unwrap())
+        .unwrap_or(0);
+    let mut body = vec![0u8; length];
+    stream.read_exact(&mut body).unwrap();
+    (header, String::from_utf8(body).unwrap())
+}
+
+/// Check if server closed connection
+fn is_closed(stream: &mut TcpStream) -> bool {
+    let mut rest = Vec::new();
+    stream.read_to_end(&mut rest).unwrap();
+    rest.is_empty()
+}
+
+#[test]
+fn keep_alive_by_default() {
+    // two requests on one connection
+    let (mut stream, server) = connect(HttpSettings::new());
+    stream.write_all(b"GET /a HTTP/1.1\r\n\r\n").unwrap();
+    let (header, body) = read_response(&mut stream);
+    assert!(!header.contains("connection: close"));
+    assert_eq!(body, "/a");
+    stream
+        .write_all(b"GET /b HTTP/1.1\r\nconnection: close\r\n\r\n")
+        .unwrap();
+    let (header, body) = read_response(&mut stream);
+    assert!(header.contains("connection: close\r\n"));
+    assert_eq!(body, "/b");
+
+    // closed after connection: close
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+}
+
+#[test]
+fn http_1_0_closes_unless_keep_alive() {
+    // HTTP/1.0 closes by default
+    let (mut stream, server) = connect(HttpSettings::new());
+    stream.write_all(b"GET /a HTTP/1.0\r\n\r\n").unwrap();
+    let (header, _) = read_response(&mut stream);
+    assert!(header.contains("connection: close\r\n"));
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+
+    // HTTP/1.0 with keep-alive
+    let (mut stream, server) = connect(HttpSettings::new());
+    stream
+        .write_all(b"GET /a HTTP/1.0\r\nconnection: keep-alive\r\n\r\n")
+        .unwrap();
+    let (header, _) = read_response(&mut stream);
+    assert!(header.contains("connection: keep-alive\r\n"));
+    stream.write_all(b"GET /b HTTP/1.0\r\n\r\n").unwrap();
+    let (_, body) = read_response(&mut stream);
+    assert_eq!(body, "/b");
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+}
+
+#[test]
+fn max_requests_closes_connection() {
+    // last allowed request gets connection: close
+    let mut http_settings = HttpSettings::new();
+    http_settings.max_requests = 2;
+    let (mut stream, server) = connect(http_settings);
+    stream.write_all(b"GET /a HTTP/1.1\r\n\r\n").unwrap();
+    let (header, _) = read_response(&mut stream);
+    assert!(!header.contains("connection: close"));
+    stream.write_all(b"GET /b HTTP/1.1\r\n\r\n").unwrap();
+    let (header, _) = read_response(&mut stream);
+    assert!(header.contains("connection: close\r\n"));
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+}
+
+#[test]
+fn idle_connection_closed_after_timeout() {
+    // no second request within keep-alive timeout
+    let mut http_settings = HttpSettings::new();
+    http_settings.keep_alive_timeout = Some(Duration::from_millis(100));
+    let (mut stream, server) = connect(http_settings);
+    stream.write_all(b"GET /a HTTP/1.1\r\n\r\n").unwrap();
+    read_response(&mut stream);
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+}
+
+#[test]
+fn pipelined_requests_in_order() {
+    // three requests in one write
+    let (mut stream, server) = connect(HttpSettings::new());
+    stream
+        .write_all(b"GET /a HTTP/1.1\r\n\r\nGET /b HTTP/1.1\r\n\r\nGET /c HTTP/1.1\r\nconnection: close\r\n\r\n")
+        .unwrap();
+    for path in ["/a", "/b", "/c"] {
+        assert_eq!(read_response(&mut stream).1, path);
+    }
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+}
+
+#[test]
+fn unread_bodies_drained() {
+    // content-length and chunked bodies not read by handler, pipelined after
+    let (mut stream, server) = connect(HttpSettings::new());
+    stream
+        .write_all(
+            b"POST /a HTTP/1.1\r\ncontent-length: 11\r\n\r\nGET /x HTTP\
+            POST /b HTTP/1.1\r\ntransfer-encoding: chunked\r\n\r\n4\r\nGET \r\n0\r\n\r\n\
+            GET /c HTTP/1.1\r\nconnection: close\r\n\r\n",
+        )
+        .unwrap();
+    for path in ["/a", "/b", "/c"] {
+        assert_eq!(read_response(&mut stream).1, path);
+    }
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+}
+
+#[test]
+fn oversized_unread_body_closes_connection() {
+    // body larger than max body size is not drained
+    let mut http_settings = HttpSettings::new();
+    http_settings.max_body_size = 4;
+    let (mut stream, server) = connect(http_settings);
+    stream
+        .write_all(
+            b"POST /a HTTP/1.1\r\ncontent-length: 10\r\n\r\n0123456789GET /b HTTP/1.1\r\n\r\n",
+        )
+        .unwrap();
+    let (header, body) = read_response(&mut stream);
+    assert!(header.contains("connection: close\r\n"));
+    assert_eq!(body, "/a");
+    assert!(is_closed(&mut stream));
+    server.join().unwrap();
+}
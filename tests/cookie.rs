@@ -0,0 +1,113 @@
+use lhi::server::{parse_cookies, Cookie, Response, SameSite};
+use std::time::{Duration, UNIX_EPOCH};
+
+#[test]
+fn parse_cookie_header() {
+    // quoted values, whitespace and repeated names in order
+    let cookies = parse_cookies(" a=1; b=\"two\" ;a=3;c=;d=x=y");
+    assert_eq!(
+        cookies.iter().collect::<Vec<_>>(),
+        vec![
+            ("a", &"1".to_string()),
+            ("b", &"two".to_string()),
+            ("a", &"3".to_string()),
+            ("c", &String::new()),
+            ("d", &"x=y".to_string()),
+        ]
+    );
+    assert_eq!(cookies.get("a").map(|a| a.as_str()), Some("1"));
+
+    // pairs without equals sign or with invalid names are skipped
+    let cookies = parse_cookies("flag; =1; a b=2; (c)=3; ok=4;;");
+    assert_eq!(
+        cookies.iter().collect::<Vec<_>>(),
+        vec![("ok", &"4".to_string())]
+    );
+    assert!(parse_cookies("").is_empty());
+}
+
+#[test]
+fn cookie_validation() {
+    // invalid names
+    for name in ["", "a b", "a=b", "a;b", "a\r\nb", "\u{e9}"] {
+        assert_eq!(
+            Cookie::new(name, "value").unwrap_err().err_msg(),
+            "Invalid cookie name",
+            "{:?}",
+            name
+        );
+    }
+
+    // invalid values
+    for value in ["a b", "\"a\"", "a,b", "a;b", "a\\b", "a\r\nb", "\u{e9}"] {
+        assert_eq!(
+            Cookie::new("name", value).unwrap_err().err_msg(),
+            "Invalid cookie value",
+            "{:?}",
+            value
+        );
+    }
+
+    // valid cookie
+    let cookie = Cookie::new("session-id", "abc/123=+!").unwrap();
+    assert_eq!(
+        (cookie.name(), cookie.value()),
+        ("session-id", "abc/123=+!")
+    );
+    assert_eq!(cookie.to_header_value(), "session-id=abc/123=+!");
+}
+
+#[test]
+fn set_cookie_formatting() {
+    // all attributes in order
+    let cookie = Cookie::new("id", "1")
+        .unwrap()
+        .set_path(Some("/app"))
+        .set_domain(Some("example.com"))
+        .set_max_age(Some(3600))
+        .set_expires(Some(UNIX_EPOCH + Duration::from_secs(784111777)))
+        .set_secure(true)
+        .set_http_only(true)
+        .set_same_site(Some(SameSite::Lax));
+    assert_eq!(
+        cookie.to_header_value(),
+        "id=1; Path=/app; Domain=example.com; Max-Age=3600; Expires=Sun, 06 Nov 1994 08:49:37 GMT; Secure; HttpOnly; SameSite=Lax"
+    );
+    assert_eq!(cookie.to_string(), cookie.to_header_value());
+
+    // attribute injection is removed
+    let cookie = Cookie::new("id", "1")
+        .unwrap()
+        .set_path(Some("/; Domain=evil.com\r\nx-a: 1"))
+        .set_same_site(Some(SameSite::None));
+    assert_eq!(
+        cookie.to_header_value(),
+        "id=1; Path=/ Domain=evil.comx-a: 1; SameSite=None"
+    );
+}
+
+#[test]
+fn removal_cookie() {
+    // empty value expiring immediately
+    let cookie = Cookie::removal("id").unwrap().set_path(Some("/"));
+    assert_eq!(
+        cookie.to_header_value(),
+        "id=; Path=/; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT"
+    );
+    assert!(Cookie::removal("a b").is_err());
+}
+
+#[test]
+fn multiple_set_cookie_headers() {
+    // one header per cookie
+    let response = Response::new()
+        .add_cookie(&Cookie::new("a", "1").unwrap())
+        .add_cookie(&Cookie::removal("b").unwrap().set_secure(true));
+    assert_eq!(
+        response.headers().get_all("set-cookie"),
+        vec![
+            "a=1",
+            "b=; Max-Age=0; Expires=Thu, 01 Jan 1970 00:00:00 GMT; Secure"
+        ]
+    );
+}
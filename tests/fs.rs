@@ -0,0 +1,158 @@
+use kern::byte::scan;
+use lhi::server::fs::FileServer;
+use lhi::server::{handle_connection, HttpSettings};
+use std::fs;
+use std::io::prelude::{Read, Write};
+use std::io::ErrorKind;
+use std::net::{TcpListener, TcpStream};
+use std::path::PathBuf;
+use std::thread;
+
+/// Create directory with root/file.txt, root/sub/index.html and outside/secret.txt
+fn setup(name: &str) -> (PathBuf, PathBuf) {
+    // create fresh directories
+    let base = std::env::temp_dir().join(format!("lhi-test-fs-{}-{}", std::process::id(), name));
+    fs::remove_dir_all(&base).ok();
+    let root = base.join("root");
+    let outside = base.join("outside");
+    fs::create_dir_all(root.join("sub")).unwrap();
+    fs::create_dir_all(&outside).unwrap();
+
+    // create files
+    fs::write(root.join("file.txt"), "file").unwrap();
+    fs::write(root.join("sub").join("index.html"), "index").unwrap();
+    fs::write(outside.join("secret.txt"), "secret").unwrap();
+    (base, root)
+}
+
+/// Send request to file server and return full response
+fn raw_request(file_server: FileServer, url: &str) -> String {
+    // serve one connection on random port
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &HttpSettings::new(), None, &file_server).unwrap();
+    });
+
+    // send request and read full response
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "GET {} HTTP/1.1\r\nconnection: close\r\n\r\n", url).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    server.join().unwrap();
+    String::from_utf8_lossy(&response).to_string()
+}
+
+/// Send request to file server and return status line and body
+fn request(file_server: FileServer, url: &str) -> (String, String) {
+    // split status line and body
+    let response = raw_request(file_server, url);
+    let status = response.lines().next().unwrap_or_default().to_string();
+    let body = scan(response.as_bytes(), b"\r\n\r\n")
+        .map(|pos| response[(pos + 4)..].to_string())
+        .unwrap_or_default();
+    (status, body)
+}
+
+#[test]
+fn resolve_within_root() {
+    let (base, root) = setup("within");
+    let file_server = FileServer::new(&root).unwrap();
+
+    // normal, dot and empty segments
+    let file = file_server.root().join("file.txt");
+    assert_eq!(file_server.resolve("file.txt").unwrap(), file);
+    assert_eq!(file_server.resolve("//./file.txt").unwrap(), file);
+
+    // absolute paths stay within root
+    assert_eq!(
+        file_server.resolve("/etc/passwd").unwrap_err().kind(),
+        ErrorKind::NotFound
+    );
+    fs::remove_dir_all(base).ok();
+}
+
+#[test]
+fn resolve_denies_escapes() {
+    let (base, root) = setup("escapes");
+    let file_server = FileServer::new(&root).unwrap();
+
+    // traversal, decoded %2f, backslash, NUL and drive separators
+    for path in [
+        "..",
+        "../outside/secret.txt",
+        "sub/../../outside/secret.txt",
+        "sub/..",
+        "..\\outside\\secret.txt",
+        "file.txt\0.html",
+        "C:/outside/secret.txt",
+    ] {
+        assert_eq!(
+            file_server.resolve(path).unwrap_err().kind(),
+            ErrorKind::PermissionDenied,
+            "{:?}",
+            path
+        );
+    }
+    fs::remove_dir_all(base).ok();
+}
+
+#[cfg(unix)]
+#[test]
+fn resolve_denies_symlink_escape() {
+    let (base, root) = setup("symlink");
+    std::os::unix::fs::symlink(base.join("outside"), root.join("link")).unwrap();
+    std::os::unix::fs::symlink(root.join("file.txt"), root.join("inner")).unwrap();
+    let file_server = FileServer::new(&root).unwrap();
+
+    // symlink out of root is denied, within root is followed
+    assert_eq!(
+        file_server.resolve("link/secret.txt").unwrap_err().kind(),
+        ErrorKind::PermissionDenied
+    );
+    assert_eq!(
+        file_server.resolve("inner").unwrap(),
+        file_server.root().join("file.txt")
+    );
+    fs::remove_dir_all(base).ok();
+}
+
+#[test]
+fn serve_errors_hide_filesystem_paths() {
+    let (base, root) = setup("errors");
+    let file_server = FileServer::new(&root).unwrap();
+    let root_text = file_server.root().to_string_lossy().to_string();
+
+    // not found
+    let (status, body) = request(file_server.clone(), "/missing.txt");
+    assert_eq!(status, "HTTP/1.1 404 Not Found");
+    assert!(body.contains("missing.txt") && !body.contains(&root_text));
+
+    // encoded traversal
+    let (status, body) = request(file_server.clone(), "/..%2foutside%2fsecret.txt");
+    assert_eq!(status, "HTTP/1.1 403 Forbidden");
+    assert!(!body.contains(&root_text));
+
+    // file and index file
+    assert_eq!(request(file_server.clone(), "/file.txt").1, "file");
+    assert_eq!(request(file_server, "/sub/").1, "index");
+    fs::remove_dir_all(base).ok();
+}
+
+#[test]
+fn directory_redirect_is_relative() {
+    let (base, root) = setup("redirect");
+    fs::create_dir_all(root.join("www")).unwrap();
+    let file_server = FileServer::new(&root).unwrap();
+
+    // leading slashes do not redirect to another host
+    let response = raw_request(file_server.clone(), "//www");
+    assert!(response.starts_with("HTTP/1.1 301 Moved Permanently\r\n"));
+    assert!(response.contains("\r\nlocation: ./www/\r\n"));
+
+    // query is kept
+    let response = raw_request(file_server, "/sub?a=1");
+    assert!(response.contains("\r\nlocation: ./sub/?a=1\r\n"));
+    fs::remove_dir_all(base).ok();
+}
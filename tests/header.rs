@@ -0,0 +1,46 @@
+use lhi::server::{is_header_name, is_header_value, HeaderMap, Response};
+
+#[test]
+fn header_map_rejects_injection() {
+    // CR, LF and NUL in values or names
+    let mut headers = HeaderMap::new();
+    assert!(headers.append("x-a", "1\r\nset-cookie: a=b").is_err());
+    assert!(headers.append("x-a", "1\nx-b: 2").is_err());
+    assert!(headers.append("x-a", "1\0").is_err());
+    assert!(headers.append("x-a\r\nx-b", "1").is_err());
+    assert!(headers.append("x a", "1").is_err());
+    assert!(headers.append("", "1").is_err());
+    assert!(headers.insert("x-a", "\r\n").is_err());
+    assert!(headers.is_empty());
+
+    // tab and visible characters are allowed
+    assert!(is_header_value("a\tb ~\u{e9}"));
+    assert!(is_header_name("X-Custom_1!"));
+    assert!(!is_header_name("x-a "));
+
+    // builder ignores invalid headers instead of writing them
+    let raw = Response::new()
+        .set_header("x-a", "1\r\nx-injected: 1")
+        .add_header("x-b\r\n", "2")
+        .into_bytes();
+    let raw = String::from_utf8(raw).unwrap();
+    assert!(!raw.contains("x-injected") && !raw.contains("x-a") && !raw.contains("x-b"));
+}
+
+#[test]
+fn header_map_multiple_values() {
+    // names are case-insensitive, values keep order
+    let mut headers = HeaderMap::new();
+    headers.append("Accept", "a").unwrap();
+    headers.append("x-other", "b").unwrap();
+    headers.append("accept", "c").unwrap();
+    assert_eq!(headers.get("ACCEPT"), Some("a"));
+    assert_eq!(headers.get_all("accept"), vec!["a", "c"]);
+    assert!(headers.contains("X-Other"));
+
+    // insert replaces all values
+    headers.insert("ACCEPT", "d").unwrap();
+    assert_eq!(headers.get_all("accept"), vec!["d"]);
+    assert!(headers.remove("accept") && !headers.remove("accept"));
+    assert_eq!(headers.len(), 1);
+}
@@ -0,0 +1,36 @@
+use lhi::server::HttpMethod;
+
+#[test]
+fn parse_standard_methods() {
+    // case-sensitive standard methods
+    for token in [
+        "GET", "HEAD", "POST", "PUT", "DELETE", "CONNECT", "OPTIONS", "TRACE", "PATCH",
+    ] {
+        let method = HttpMethod::parse(token).unwrap();
+        assert!(!method.is_extension());
+        assert_eq!(method.as_str(), token);
+    }
+    assert_eq!(HttpMethod::parse("GET").unwrap(), HttpMethod::GET);
+    assert_eq!("PATCH".parse::<HttpMethod>().unwrap(), HttpMethod::PATCH);
+}
+
+#[test]
+fn parse_extension_methods() {
+    // other tokens including lowercase standard names
+    for token in ["get", "PROPFIND", "M-SEARCH", "X_1.2!"] {
+        let method = HttpMethod::parse(token).unwrap();
+        assert_eq!(method, HttpMethod::Extension(token.to_string()));
+        assert!(method.is_extension());
+        assert_eq!(method.to_string(), token);
+    }
+
+    // malformed tokens
+    for token in ["", "G T", "GET(", "G\"T", "G/T", "G\u{e9}T", "G\r\nT"] {
+        assert_eq!(
+            HttpMethod::parse(token).unwrap_err().err_msg(),
+            "Malformed method in header",
+            "{:?}",
+            token
+        );
+    }
+}
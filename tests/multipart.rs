@@ -0,0 +1,163 @@
+use kern::Fail;
+use lhi::server::{handle_connection, HttpRequest, HttpSettings, MultipartReader, Response};
+use std::io::prelude::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// Multipart body with a text field and a file
+const BODY: &str = "--XyZ\r\n\
+    content-disposition: form-data; name=\"title\"\r\n\r\n\
+    hello\r\n\
+    --XyZ\r\n\
+    content-disposition: form-data; name=\"upload\"; filename=\"a.txt\"\r\n\
+    content-type: text/plain\r\n\r\n\
+    file content\r\n\
+    --XyZ--\r\n";
+
+/// Reader returning at most a few bytes per read
+struct SlowReader<'a> {
+    data: &'a [u8],
+    step: usize,
+}
+
+impl Read for SlowReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let length = self.step.min(buf.len()).min(self.data.len());
+        buf[..length].copy_from_slice(&self.data[..length]);
+        self.data = &self.data[length..];
+        Ok(length)
+    }
+}
+
+/// Settings reading whole body before calling handler
+fn buffered() -> HttpSettings {
+    let mut http_settings = HttpSettings::new();
+    http_settings.buffer_body = true;
+    http_settings
+}
+
+/// Send multipart request with body to handler and return full response
+fn request<F>(http_settings: HttpSettings, body: &str, handler: F) -> String
+where
+    F: Fn(Result<HttpRequest, Fail>) -> Result<Response, Fail> + Send + Sync + 'static,
+{
+    // serve one connection on random port
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &http_settings, None, &handler).unwrap();
+    });
+
+    // send request and read full response
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "POST / HTTP/1.1\r\nconnection: close\r\ncontent-type: multipart/form-data; boundary=XyZ\r\ncontent-length: {}\r\n\r\n{}",
+        body.len(),
+        body
+    )
+    .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    server.join().unwrap();
+    String::from_utf8_lossy(&response).to_string()
+}
+
+#[test]
+fn buffered_files_only_in_parts() {
+    let response = request(buffered(), BODY, |req| {
+        let req = req?;
+
+        // text field in parameters, file only in parts
+        assert_eq!(
+            req.post().get("title").map(|v| v.as_slice()),
+            Some(&b"hello"[..])
+        );
+        assert!(req.post().get("upload").is_none());
+        assert!(req.post_all("upload").is_empty());
+        assert_eq!(req.parts().len(), 2);
+        let upload = req.part("upload").unwrap();
+        assert_eq!(upload.filename(), Some("a.txt"));
+        assert_eq!(upload.data(), b"file content");
+        Ok(Response::new().set_body("ok"))
+    });
+    assert!(response.ends_with("\r\n\r\nok"));
+}
+
+#[test]
+fn buffered_broken_multipart_fails() {
+    // truncated body and unknown boundary
+    for body in [&BODY[..40], "--other\r\n\r\nx\r\n--other--\r\n"] {
+        let response = request(buffered(), body, |req| {
+            assert!(req.is_err());
+            req?;
+            Ok(Response::new())
+        });
+        assert!(response.starts_with("HTTP/1.1 400 "), "{:?}", body);
+    }
+}
+
+#[test]
+fn streaming_boundary_split_across_reads() {
+    // every read size splits boundaries and headers differently
+    for step in 1..=BODY.len() {
+        let reader = SlowReader {
+            data: BODY.as_bytes(),
+            step,
+        };
+        let parts = MultipartReader::new(reader, "XyZ")
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(parts.len(), 2, "step {}", step);
+        assert_eq!(parts[0].data(), b"hello", "step {}", step);
+        assert_eq!(parts[1].data(), b"file content", "step {}", step);
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn spooled_file_only_readable_by_owner() {
+    use std::os::unix::fs::PermissionsExt;
+
+    // spool every file part
+    let reader = SlowReader {
+        data: BODY.as_bytes(),
+        step: 7,
+    };
+    let parts = MultipartReader::new(reader, "XyZ")
+        .set_spool_threshold(Some(0))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    // temp file has mode 600
+    let path = parts[1].path().unwrap();
+    let mode = std::fs::metadata(path).unwrap().permissions().mode();
+    assert_eq!(mode & 0o777, 0o600);
+}
+
+#[test]
+fn streaming_limited_to_max_body_size() {
+    // parts exceed max body size
+    let mut http_settings = HttpSettings::new();
+    http_settings.max_body_size = 10;
+    let response = request(http_settings, BODY, |req| {
+        let mut req = req?;
+        let parts = req.multipart()?.collect::<Result<Vec<_>, _>>();
+        assert_eq!(
+            parts.unwrap_err().to_string(),
+            "Max multipart body size exceeded"
+        );
+        Ok(Response::new().set_body("ok"))
+    });
+    assert!(response.ends_with("\r\n\r\nok"));
+
+    // parts within max body size
+    let response = request(HttpSettings::new(), BODY, |req| {
+        let mut req = req?;
+        let parts = req.multipart()?.collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(parts.len(), 2);
+        Ok(Response::new().set_body("ok"))
+    });
+    assert!(response.ends_with("\r\n\r\nok"));
+}
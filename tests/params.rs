@@ -0,0 +1,24 @@
+use lhi::server::Parameters;
+
+#[test]
+fn parameters_keep_order_and_case() {
+    // repeated keys with different case
+    let mut params = Parameters::new();
+    params.add("A", 1);
+    params.add("a", 2);
+    params.add("A", 3);
+    assert_eq!(params.get_all("A"), vec![&1, &3]);
+    assert_eq!(params.get_all("a"), vec![&2]);
+    assert!(params.get_all("b").is_empty());
+
+    // first value, iteration order and map of last values
+    assert_eq!(params.get("A"), Some(&1));
+    assert!(!params.contains("b"));
+    assert_eq!(params.len(), 3);
+    assert_eq!(
+        params.iter().collect::<Vec<_>>(),
+        vec![("A", &1), ("a", &2), ("A", &3)]
+    );
+    let map = params.to_map();
+    assert_eq!((map["A"], map["a"], map.len()), (3, 2, 2));
+}
@@ -0,0 +1,166 @@
+use lhi::server::{apply_range, parse_range, Compression, Response, ResponseBody, StatusCode};
+use std::io::prelude::{Read, Seek};
+use std::io::{Cursor, SeekFrom};
+use std::ops::Range;
+
+/// Response with seekable body positioned after a prefix
+fn seekable_response(prefix: &str, content: &str) -> Response {
+    let mut cursor = Cursor::new(format!("{}{}", prefix, content).into_bytes());
+    cursor.seek(SeekFrom::Start(prefix.len() as u64)).unwrap();
+    Response::new()
+        .set_content_type("text/plain")
+        .set_header("etag", "\"v1\"")
+        .set_body_seekable(cursor, content.len() as u64)
+}
+
+/// Create expected ranges from inclusive start and exclusive end
+fn ranges(bounds: &[(u64, u64)]) -> Option<Vec<Range<u64>>> {
+    Some(bounds.iter().map(|&(start, end)| start..end).collect())
+}
+
+/// Read body of response
+fn body(mut response: Response) -> Vec<u8> {
+    let mut body = Vec::new();
+    match response.take_body() {
+        ResponseBody::Bytes(bytes) => body = bytes,
+        ResponseBody::Reader(mut reader, _) => {
+            reader.read_to_end(&mut body).unwrap();
+        }
+        ResponseBody::Seekable(mut reader, _) => {
+            reader.read_to_end(&mut body).unwrap();
+        }
+        ResponseBody::Writer(_) => panic!("unexpected writer body"),
+    }
+    body
+}
+
+#[test]
+fn parse_range_specs() {
+    // closed, open and suffix ranges
+    assert_eq!(parse_range("bytes=0-2", 10), ranges(&[(0, 3)]));
+    assert_eq!(parse_range("bytes=7-", 10), ranges(&[(7, 10)]));
+    assert_eq!(parse_range("bytes=-3", 10), ranges(&[(7, 10)]));
+    assert_eq!(parse_range("bytes=-30", 10), ranges(&[(0, 10)]));
+    assert_eq!(parse_range("bytes=5-100", 10), ranges(&[(5, 10)]));
+    assert_eq!(
+        parse_range("BYTES = 0-0 , 2-3", 10),
+        ranges(&[(0, 1), (2, 4)])
+    );
+
+    // unsatisfiable ranges
+    assert_eq!(parse_range("bytes=10-", 10), ranges(&[]));
+    assert_eq!(parse_range("bytes=-0", 10), ranges(&[]));
+
+    // ignored headers
+    assert_eq!(parse_range("items=0-2", 10), None);
+    assert_eq!(parse_range("bytes=3-1", 10), None);
+    assert_eq!(parse_range("bytes=a-b", 10), None);
+    assert_eq!(parse_range("bytes=+1-2", 10), None);
+    assert_eq!(parse_range("bytes=0", 10), None);
+    let many = format!("bytes={}", vec!["0-0"; 33].join(","));
+    assert_eq!(parse_range(&many, 10), None);
+}
+
+#[test]
+fn apply_range_relative_to_position() {
+    // full body starts at current position
+    let response = apply_range(seekable_response("HEADER", "payload"), None, None);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header("accept-ranges"), Some("bytes"));
+    assert_eq!(body(response), b"payload");
+
+    // single range
+    let response = apply_range(
+        seekable_response("HEADER", "payload"),
+        Some("bytes=0-2"),
+        None,
+    );
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(response.header("content-range"), Some("bytes 0-2/7"));
+    assert_eq!(body(response), b"pay");
+
+    // suffix range
+    let response = apply_range(
+        seekable_response("HEADER", "payload"),
+        Some("bytes=-4"),
+        None,
+    );
+    assert_eq!(body(response), b"load");
+}
+
+#[test]
+fn apply_range_multiple() {
+    // multipart/byteranges with both ranges
+    let response = apply_range(
+        seekable_response("HEADER", "payload"),
+        Some("bytes=0-0,4-6"),
+        None,
+    );
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let content_type = response.header("content-type").unwrap().to_string();
+    let boundary = content_type
+        .strip_prefix("multipart/byteranges; boundary=")
+        .unwrap()
+        .to_string();
+    let length = response.body().len().unwrap();
+    let body = String::from_utf8(body(response)).unwrap();
+    assert_eq!(body.len() as u64, length);
+    assert!(body.contains("content-range: bytes 0-0/7\r\n\r\np\r\n"));
+    assert!(body.contains("content-range: bytes 4-6/7\r\n\r\noad\r\n"));
+    assert!(body.ends_with(&format!("--{}--\r\n", boundary)));
+}
+
+#[test]
+fn apply_range_unsatisfiable_and_if_range() {
+    // not satisfiable
+    let response = apply_range(seekable_response("", "payload"), Some("bytes=7-"), None);
+    assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(response.header("content-range"), Some("bytes */7"));
+
+    // matching and outdated If-Range
+    let response = apply_range(
+        seekable_response("", "payload"),
+        Some("bytes=0-2"),
+        Some("\"v1\""),
+    );
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    let response = apply_range(
+        seekable_response("", "payload"),
+        Some("bytes=0-2"),
+        Some("\"v0\""),
+    );
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(body(response), b"payload");
+
+    // only 200 responses with seekable body
+    let response = Response::new().set_body("payload");
+    let response = apply_range(response, Some("bytes=0-2"), None);
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.header("accept-ranges"), None);
+}
+
+#[test]
+fn compression_keeps_seekable_ranges() {
+    // seekable body without range is compressed
+    let content = "payload ".repeat(200);
+    let response =
+        Compression::new().compress_range(seekable_response("", &content), Some("gzip"), None);
+    assert_eq!(response.header("content-encoding"), Some("gzip"));
+    assert_eq!(response.body().len(), None);
+    let response = apply_range(response, None, None);
+    assert_eq!(response.header("accept-ranges"), None);
+
+    // seekable body of range request is not compressed
+    let response = Compression::new().compress_range(
+        seekable_response("", &content),
+        Some("gzip"),
+        Some("bytes=0-6"),
+    );
+    assert_eq!(response.header("content-encoding"), None);
+    assert_eq!(response.body().len(), Some(content.len() as u64));
+
+    // range still applies
+    let response = apply_range(response, Some("bytes=0-6"), None);
+    assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(body(response), b"payload");
+}
@@ -0,0 +1,259 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use kern::Fail;
+use lhi::server::{handle_connection, HttpRequest, HttpSettings, Response};
+use std::io::prelude::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// Send raw request to handler on one connection and return full response
+fn exchange<F>(http_settings: HttpSettings, handler: F, request: &[u8]) -> String
+where
+    F: Fn(Result<HttpRequest, Fail>) -> Result<Response, Fail> + Send + Sync + 'static,
+{
+    // serve one connection on random port
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &http_settings, None, &handler).ok();
+    });
+
+    // send request and read until closed
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream.write_all(request).unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).ok();
+    server.join().unwrap();
+    String::from_utf8_lossy(&response).to_string()
+}
+
+/// Handler responding with buffered body
+fn echo(req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+    let mut req = req?;
+    let body = req.buffer_body()?.to_vec();
+    Ok(Response::new().set_body(body))
+}
+
+#[test]
+fn content_length_digits_only() {
+    // signs and whitespace inside are rejected
+    for length in ["+5", "-5", "5 5", "0x5"] {
+        let request = format!(
+            "POST / HTTP/1.1\r\ncontent-length: {}\r\nconnection: close\r\n\r\nhello",
+            length
+        );
+        let response = exchange(HttpSettings::new(), echo, request.as_bytes());
+        assert!(response.starts_with("HTTP/1.1 400 "), "{:?}", length);
+    }
+
+    // plain digits
+    let response = exchange(
+        HttpSettings::new(),
+        echo,
+        b"POST / HTTP/1.1\r\ncontent-length: 5\r\nconnection: close\r\n\r\nhello",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n") && response.ends_with("\r\n\r\nhello"));
+}
+
+#[test]
+fn malformed_header_lines_rejected() {
+    // whitespace before colon, missing colon and obsolete line folding
+    for line in [
+        "transfer-encoding : chunked",
+        "x-no-colon",
+        " x-folded: 1",
+        "x-a\tb: 1",
+    ] {
+        let request = format!(
+            "POST / HTTP/1.1\r\n{}\r\nconnection: close\r\n\r\n0\r\n\r\n",
+            line
+        );
+        let response = exchange(HttpSettings::new(), echo, request.as_bytes());
+        assert!(response.starts_with("HTTP/1.1 400 "), "{:?}", line);
+    }
+
+    // malformed trailer line
+    let response = exchange(
+        HttpSettings::new(),
+        echo,
+        b"POST / HTTP/1.1\r\ntransfer-encoding: chunked\r\nconnection: close\r\n\r\n1\r\na\r\n0\r\nx-trailer : 1\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 400 "));
+}
+
+/// Compress data with gzip
+fn gzip(data: &[u8]) -> Vec<u8> {
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.finish().unwrap()
+}
+
+/// Create POST request with gzip body
+fn gzip_request(body: &[u8]) -> Vec<u8> {
+    let mut request = format!(
+        "POST / HTTP/1.1\r\ncontent-encoding: gzip\r\ncontent-length: {}\r\nconnection: close\r\n\r\n",
+        body.len()
+    )
+    .into_bytes();
+    request.extend_from_slice(body);
+    request
+}
+
+#[test]
+fn gzip_body_decoded() {
+    // compressed body
+    let response = exchange(HttpSettings::new(), echo, &gzip_request(&gzip(b"hello")));
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n") && response.ends_with("\r\n\r\nhello"));
+
+    // empty body is not decoded
+    let response = exchange(
+        HttpSettings::new(),
+        echo,
+        b"GET / HTTP/1.1\r\ncontent-encoding: gzip\r\nconnection: close\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+}
+
+#[test]
+fn gzip_bomb_limited() {
+    // small payload expanding past max body size
+    let bomb = gzip(&vec![0u8; 1_048_576]);
+    assert!(bomb.len() < 4096);
+    for buffer_body in [false, true] {
+        let mut http_settings = HttpSettings::new();
+        http_settings.max_body_size = 65536;
+        http_settings.buffer_body = buffer_body;
+        let response = exchange(http_settings, echo, &gzip_request(&bomb));
+        assert!(
+            response.starts_with("HTTP/1.1 413 Content Too Large\r\n"),
+            "{}",
+            buffer_body
+        );
+    }
+}
+
+/// Handler responding with path, GET and POST parameters
+fn params(req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+    let mut req = req?;
+    req.buffer_body()?;
+    let body = format!(
+        "{}|{:?}|{:?}",
+        req.path(),
+        req.get_params().iter().collect::<Vec<_>>(),
+        req.post_utf8()
+    );
+    Ok(Response::new().set_body(body))
+}
+
+/// Send GET or urlencoded POST request to params handler and return body or status line if failed
+fn send_params(url: &str, form: &str) -> String {
+    let request = format!(
+        "POST {} HTTP/1.1\r\ncontent-type: application/x-www-form-urlencoded\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+        url,
+        form.len(),
+        form
+    );
+    let response = exchange(HttpSettings::new(), params, request.as_bytes());
+    match response.starts_with("HTTP/1.1 200 OK\r\n") {
+        true => response.split("\r\n\r\n").nth(1).unwrap().to_string(),
+        false => response.lines().next().unwrap().to_string(),
+    }
+}
+
+#[test]
+fn percent_decoded_path_and_parameters() {
+    // plus is literal in path and space in query and form
+    assert_eq!(
+        send_params("/a%20b+c/%E2%82%AC?q=x+y%2B&k%20=1", "f=a+b%26c&g"),
+        r#"/a b+c/€|[("q", "x y+"), ("k ", "1")]|{"f": "a b&c", "g": ""}"#
+    );
+
+    // invalid encodings are errors
+    for (url, form) in [("/%zz", ""), ("/?q=%", ""), ("/", "f=%E"), ("/%ff", "")] {
+        assert!(
+            send_params(url, form).starts_with("HTTP/1.1 400 "),
+            "{:?}",
+            (url, form)
+        );
+    }
+}
+
+/// Handler responding with values of key A and a of GET and POST parameters
+fn repeated(req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+    let mut req = req?;
+    req.buffer_body()?;
+    let body = format!(
+        "{:?} {:?} {:?} {:?} {:?} {:?}",
+        req.get_all("A"),
+        req.get_all("a"),
+        req.get().get("A"),
+        req.get_raw().get_all("A"),
+        req.post_all("A"),
+        req.post_all("a"),
+    );
+    Ok(Response::new().set_body(body))
+}
+
+#[test]
+fn repeated_parameters_keep_order_and_case() {
+    // repeated GET and POST keys with different case
+    let response = exchange(
+        HttpSettings::new(),
+        repeated,
+        b"POST /?A=1&a=2&A=%33 HTTP/1.1\r\ncontent-type: application/x-www-form-urlencoded\r\ncontent-length: 11\r\nconnection: close\r\n\r\nA=x&a=y&A=z",
+    );
+    assert!(response.ends_with(r#"["1", "3"] ["2"] Some("3") ["1", "%33"] [[120], [122]] [[121]]"#));
+}
+
+/// Handler responding with request cookies
+fn cookies(req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+    let req = req?;
+    let body = format!("{:?} {:?}", req.cookie("a"), req.cookies().get_all("b"));
+    Ok(Response::new().set_body(body))
+}
+
+#[test]
+fn cookies_from_multiple_headers() {
+    // cookie headers are combined in order
+    let response = exchange(
+        HttpSettings::new(),
+        cookies,
+        b"GET / HTTP/1.1\r\ncookie: a=1; b=2\r\ncookie: b=\"3\"; a=4\r\nconnection: close\r\n\r\n",
+    );
+    assert!(response.ends_with(r#"Some("1") ["2", "3"]"#));
+}
+
+/// Handler responding with request method
+fn method(req: Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+    let req = req?;
+    Ok(Response::new().set_body(req.method().to_string()))
+}
+
+#[test]
+fn extension_methods_not_implemented() {
+    // rejected by default
+    let request = b"PROPFIND / HTTP/1.1\r\nconnection: close\r\n\r\n";
+    let response = exchange(HttpSettings::new(), method, request);
+    assert!(response.starts_with("HTTP/1.1 501 Not Implemented\r\n"));
+
+    // passed to handler if allowed
+    let mut http_settings = HttpSettings::new();
+    http_settings.allow_extension_methods = true;
+    let response = exchange(http_settings, method, request);
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.ends_with("\r\n\r\nPROPFIND"));
+}
+
+#[test]
+fn malformed_method_rejected() {
+    // invalid token characters even if extension methods are allowed
+    let mut http_settings = HttpSettings::new();
+    http_settings.allow_extension_methods = true;
+    let response = exchange(
+        http_settings,
+        method,
+        b"GE(T / HTTP/1.1\r\nconnection: close\r\n\r\n",
+    );
+    assert!(response.starts_with("HTTP/1.1 400 Bad Request\r\n"));
+}
@@ -0,0 +1,142 @@
+use kern::byte::scan;
+use kern::Fail;
+use lhi::server::{
+    handle_connection, respond, HttpRequest, HttpSettings, Response, ResponseData, StatusCode,
+};
+use std::convert::TryFrom;
+use std::io::prelude::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// Binary content with every byte value and line endings
+fn binary_content() -> Vec<u8> {
+    let mut content: Vec<u8> = (0..=255).collect();
+    content.extend_from_slice(b"\r\n\r\n\n\r");
+    content.extend((0..=255).rev());
+    content
+}
+
+#[test]
+fn respond_binary_body_exact() {
+    // create response
+    let content = binary_content();
+    let response = respond(&content, "application/octet-stream", None);
+
+    // split header and body
+    let pos = scan(&response, b"\r\n\r\n").unwrap();
+    let header = String::from_utf8_lossy(&response[..pos]).to_string();
+    let body = &response[(pos + 4)..];
+
+    // compare body and content length
+    assert_eq!(body, &content[..]);
+    assert!(header.contains(&format!("content-length: {}", content.len())));
+}
+
+#[test]
+fn respond_binary_round_trip() {
+    // serve one connection on random port
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let handler = |req: Result<HttpRequest, Fail>| {
+            req?;
+            Ok(respond(binary_content(), "application/octet-stream", None))
+        };
+        handle_connection(stream, &HttpSettings::new(), None, &handler).unwrap();
+    });
+
+    // send request and read full response
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"GET /file HTTP/1.1\r\nconnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    server.join().unwrap();
+
+    // compare body byte-for-byte
+    let pos = scan(&response, b"\r\n\r\n").unwrap();
+    assert_eq!(&response[(pos + 4)..], &binary_content()[..]);
+}
+
+#[test]
+fn respond_invalid_status_is_server_error() {
+    // valid status is parsed
+    let raw = respond(
+        "x",
+        "text/plain",
+        Some(ResponseData::new().set_status("404 Not Found")),
+    );
+    let response = Response::try_from(raw).unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+    // invalid status is sent as 500
+    let raw = respond(
+        "x",
+        "text/plain",
+        Some(ResponseData::new().set_status("2OO OK")),
+    );
+    assert!(String::from_utf8_lossy(&raw).starts_with("HTTP/1.1 500 Internal Server Error\r\n"));
+    let response = Response::try_from(raw).unwrap();
+    assert_eq!(response.status(), StatusCode::INTERNAL_SERVER_ERROR);
+}
+
+#[test]
+fn invalid_handler_bytes_are_server_error() {
+    // serve one connection with handler returning broken bytes
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        let handler = |req: Result<HttpRequest, Fail>| {
+            req?;
+            Ok(b"HTTP/1.1 2OO OK\r\n\r\nbody".to_vec())
+        };
+        handle_connection(stream, &HttpSettings::new(), None, &handler).unwrap();
+    });
+
+    // send request and read full response
+    let mut stream = TcpStream::connect(addr).unwrap();
+    stream
+        .write_all(b"GET / HTTP/1.1\r\nconnection: close\r\n\r\n")
+        .unwrap();
+    let mut response = Vec::new();
+    stream.read_to_end(&mut response).unwrap();
+    server.join().unwrap();
+
+    // 500 instead of 400
+    assert!(
+        String::from_utf8_lossy(&response).starts_with("HTTP/1.1 500 Internal Server Error\r\n")
+    );
+}
+
+#[test]
+fn framing_headers_are_generated() {
+    // user content-length is replaced
+    let raw = Response::new()
+        .set_header("content-length", "5")
+        .set_header("transfer-encoding", "chunked")
+        .set_body("hello")
+        .into_bytes();
+    let raw = String::from_utf8(raw).unwrap();
+    assert_eq!(raw.matches("content-length").count(), 1);
+    assert!(raw.contains("content-length: 5\r\n") && !raw.contains("transfer-encoding"));
+
+    // chunked serialized response is decoded
+    let chunked = Response::new()
+        .set_body_reader(&b"chunked content"[..], None)
+        .into_bytes();
+    assert!(String::from_utf8_lossy(&chunked).contains("transfer-encoding: chunked\r\n"));
+    let response = Response::try_from(chunked).unwrap();
+    assert_eq!(response.header("transfer-encoding"), None);
+    assert_eq!(response.body().bytes(), Some(&b"chunked content"[..]));
+    let raw = String::from_utf8(response.into_bytes()).unwrap();
+    assert!(raw.contains("content-length: 15\r\n") && !raw.contains("transfer-encoding"));
+
+    // incomplete or unknown transfer codings are rejected
+    assert!(
+        Response::parse(b"HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n5\r\nab").is_err()
+    );
+    assert!(Response::parse(b"HTTP/1.1 200 OK\r\ntransfer-encoding: gzip\r\n\r\nab").is_err());
+}
@@ -0,0 +1,152 @@
+use kern::Fail;
+use lhi::server::router::Router;
+use lhi::server::{handle_connection, HttpMethod, HttpRequest, HttpSettings, Response};
+use std::io::prelude::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+/// Handler responding with name, method and path parameters
+fn named(name: &'static str) -> impl Fn(Result<HttpRequest, Fail>) -> Result<Response, Fail> {
+    move |req| {
+        let req = req?;
+        let body = format!("{} {} {:?}", name, req.method(), req.params());
+        Ok(Response::new().set_body(body))
+    }
+}
+
+/// Router with static, parameter and wildcard routes
+fn router() -> Router {
+    Router::new()
+        .get("/users", named("list"))
+        .and_then(|router| router.post("/users", named("create")))
+        .and_then(|router| router.get("/users/:id", named("show")))
+        .and_then(|router| router.route("DELETE /users/:id", named("delete")))
+        .and_then(|router| router.get("/users/:id/posts/:post", named("post")))
+        .and_then(|router| router.get("/static/*path", named("static")))
+        .and_then(|router| router.any("/any", named("any")))
+        .unwrap()
+}
+
+/// Send request line to router on one connection, returns status line, allow header and body
+fn request(router: Arc<Router>, request_line: &str) -> (String, Option<String>, String) {
+    // serve one connection on random port
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = thread::spawn(move || {
+        let (stream, _) = listener.accept().unwrap();
+        handle_connection(stream, &HttpSettings::new(), None, &*router).ok();
+    });
+
+    // send request and read until closed
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(stream, "{}\r\nconnection: close\r\n\r\n", request_line).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    server.join().unwrap();
+
+    // split response
+    let (header, body) = response.split_once("\r\n\r\n").unwrap();
+    let allow = header
+        .lines()
+        .find_map(|line| line.strip_prefix("allow: "))
+        .map(|allow| allow.to_string());
+    let status = header.lines().next().unwrap().to_string();
+    (status, allow, body.to_string())
+}
+
+#[test]
+fn routes_by_method_and_path() {
+    // static routes by method
+    let router = Arc::new(router());
+    let ok = |name: &str| ("HTTP/1.1 200 OK".to_string(), None, name.to_string());
+    assert_eq!(
+        request(router.clone(), "GET /users HTTP/1.1"),
+        ok("list GET {}")
+    );
+    assert_eq!(
+        request(router.clone(), "POST /users HTTP/1.1"),
+        ok("create POST {}")
+    );
+    assert_eq!(
+        request(router.clone(), "PATCH /any HTTP/1.1"),
+        ok("any PATCH {}")
+    );
+
+    // percent-decoded path parameters
+    assert_eq!(
+        request(router.clone(), "DELETE /users/a%20b HTTP/1.1"),
+        ok(r#"delete DELETE {"id": "a b"}"#)
+    );
+    assert_eq!(
+        request(router.clone(), "GET /users/1/posts/2?x=y HTTP/1.1"),
+        ok(r#"post GET {"id": "1", "post": "2"}"#)
+    );
+
+    // wildcard captures rest of path
+    assert_eq!(
+        request(router.clone(), "GET /static/css/a%2Fb.css HTTP/1.1"),
+        ok(r#"static GET {"path": "css/a/b.css"}"#)
+    );
+    assert_eq!(
+        request(router, "GET /static/ HTTP/1.1"),
+        ok(r#"static GET {"path": ""}"#)
+    );
+}
+
+#[test]
+fn head_uses_get_routes() {
+    // HEAD response without body
+    let (status, allow, body) = request(Arc::new(router()), "HEAD /users/1 HTTP/1.1");
+    assert_eq!(
+        (status.as_str(), allow, body.as_str()),
+        ("HTTP/1.1 200 OK", None, "")
+    );
+}
+
+#[test]
+fn not_found() {
+    // unknown paths, empty parameters and extra segments
+    let router = Arc::new(router());
+    for request_line in [
+        "GET /unknown HTTP/1.1",
+        "GET /users/ HTTP/1.1",
+        "GET /users/1/posts HTTP/1.1",
+        "GET /users/1/posts/2/x HTTP/1.1",
+    ] {
+        let (status, allow, _) = request(router.clone(), request_line);
+        assert_eq!(status, "HTTP/1.1 404 Not Found", "{}", request_line);
+        assert_eq!(allow, None);
+    }
+}
+
+#[test]
+fn method_not_allowed() {
+    // allow header lists methods of matching routes
+    let router = Arc::new(router());
+    let (status, allow, _) = request(router.clone(), "PUT /users/1 HTTP/1.1");
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+    assert_eq!(allow.as_deref(), Some("DELETE, GET, HEAD"));
+    let (status, allow, _) = request(router, "DELETE /users HTTP/1.1");
+    assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+    assert_eq!(allow.as_deref(), Some("GET, HEAD, POST"));
+}
+
+#[test]
+fn wildcard_must_be_last() {
+    // wildcard before other segments
+    let err = Router::new()
+        .get("/static/*path/x", named("static"))
+        .err()
+        .unwrap();
+    assert_eq!(
+        err.err_msg(),
+        "Wildcard must be the last segment in /static/*path/x"
+    );
+    assert!(Router::new()
+        .add(Some(HttpMethod::GET), "/*a/*b", named("static"))
+        .is_err());
+
+    // invalid method token
+    assert!(Router::new().route("G(T /", named("bad")).is_err());
+}
@@ -0,0 +1,40 @@
+use lhi::common::{percent_decode, percent_decode_utf8, percent_encode};
+
+#[test]
+fn percent_decode_bytes() {
+    // hex digits in both cases, plus optionally as space
+    assert_eq!(percent_decode("a%20b%2fc%2F", false).unwrap(), b"a b/c/");
+    assert_eq!(percent_decode("a+b%2B", true).unwrap(), b"a b+");
+    assert_eq!(percent_decode("a+b", false).unwrap(), b"a+b");
+    assert_eq!(percent_decode("%00%ff", false).unwrap(), [0, 255]);
+
+    // incomplete and invalid sequences
+    for raw in ["%", "%2", "a%2", "%zz", "%2g", "%+1"] {
+        assert!(percent_decode(raw, true).is_err(), "{:?}", raw);
+    }
+}
+
+#[test]
+fn percent_decode_utf8_strings() {
+    // multi-byte characters
+    assert_eq!(
+        percent_decode_utf8("%E2%82%AC+1", true).unwrap(),
+        "\u{20ac} 1"
+    );
+
+    // invalid UTF-8
+    assert!(percent_decode_utf8("%ff", false).is_err());
+    assert!(percent_decode_utf8("%E2%82", false).is_err());
+}
+
+#[test]
+fn percent_encode_round_trip() {
+    // unreserved characters and slash are kept
+    assert_eq!(percent_encode("a-b_c.d~e/f"), "a-b_c.d~e/f");
+    assert_eq!(percent_encode("a b+\u{20ac}"), "a%20b%2B%E2%82%AC");
+    let raw = "x y/+%?&=\u{e9}";
+    assert_eq!(
+        percent_decode_utf8(&percent_encode(raw), false).unwrap(),
+        raw
+    );
+}